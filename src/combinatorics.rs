@@ -0,0 +1,124 @@
+//! Factorials, permutations and combinations.
+
+use std::f64::consts::PI;
+
+use num_bigint::BigInt;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+
+use crate::value::{MAX_EXACT_BITS, Value};
+
+/// Lanczos approximation coefficients, for g = 7.
+const LANCZOS_G: f64 = 7.0;
+const LANCZOS: [f64; 9] = [
+    0.999_999_999_999_809_9,
+    676.520_368_121_885_1,
+    -1_259.139_216_722_402_8,
+    771.323_428_777_653_1,
+    -176.615_029_162_140_6,
+    12.507_343_278_686_905,
+    -0.138_571_095_265_720_12,
+    9.984_369_578_019_572e-6,
+    1.505_632_735_149_311_6e-7,
+];
+
+/// The gamma function, which extends `(x - 1)!` to real numbers.
+pub fn gamma(x: f64) -> f64 {
+    if x <= 0.0 && x.fract() == 0.0 {
+        return f64::NAN;
+    }
+    if x > 171.7 {
+        return f64::INFINITY;
+    }
+    if x < 0.5 {
+        // Reflection formula.
+        return PI / ((PI * x).sin() * gamma(1.0 - x));
+    }
+    let x = x - 1.0;
+    let t = x + LANCZOS_G + 0.5;
+    let sum = LANCZOS
+        .iter()
+        .enumerate()
+        .skip(1)
+        .fold(LANCZOS[0], |sum, (i, c)| sum + c / (x + i as f64));
+    // Split the power so it doesn't overflow before the exponential shrinks it.
+    let power = t.powf((x + 0.5) / 2.0);
+    (2.0 * PI).sqrt() * power * ((-t).exp() * power) * sum
+}
+
+/// `n × (n - 1) × … × (n - k + 1)`, when the result is small enough to keep
+/// exact.
+fn falling(n: &BigInt, k: &BigInt) -> Option<BigInt> {
+    let k = k.to_u64()?;
+    if k.saturating_mul(n.bits()) > MAX_EXACT_BITS {
+        return None;
+    }
+    Some((0..k).fold(BigInt::one(), |product, i| product * (n - i)))
+}
+
+/// `n!`, or `None` for negative or very large `n`.
+pub fn exact_factorial(n: &BigInt) -> Option<BigInt> {
+    if n.is_negative() {
+        return None;
+    }
+    falling(n, n)
+}
+
+/// Ways to arrange `k` of `n` items, or `None` when that isn't a whole
+/// number or is very large.
+pub fn exact_permutations(n: &BigInt, k: &BigInt) -> Option<BigInt> {
+    if n.is_negative() || k.is_negative() {
+        return None;
+    }
+    if k > n {
+        return Some(BigInt::zero());
+    }
+    falling(n, k)
+}
+
+/// Ways to choose `k` of `n` items, or `None` when that isn't a whole number
+/// or is very large.
+pub fn exact_choose(n: &BigInt, k: &BigInt) -> Option<BigInt> {
+    if n.is_negative() || k.is_negative() {
+        return None;
+    }
+    if k > n {
+        return Some(BigInt::zero());
+    }
+    let k = k.min(&(n - k)).clone();
+    Some(falling(n, &k)? / exact_factorial(&k)?)
+}
+
+pub fn factorial(n: Value) -> Value {
+    if let Value::List(items) = n {
+        return Value::List(items.into_iter().map(factorial).collect());
+    }
+    match n.to_whole().and_then(|n| exact_factorial(&n)) {
+        Some(result) => Value::from(result),
+        None => Value::Float(gamma(n.to_f64() + 1.0)),
+    }
+}
+
+/// `nPr`, which like `nCr` has no value for negative integers.
+pub fn permutations(n: Value, k: Value) -> Value {
+    if let (Some(n), Some(k)) = (n.to_whole(), k.to_whole()) {
+        if n.is_negative() || k.is_negative() {
+            return Value::Float(f64::NAN);
+        }
+        if let Some(result) = exact_permutations(&n, &k) {
+            return Value::from(result);
+        }
+    }
+    let (n, k) = (n.to_f64(), k.to_f64());
+    Value::Float(gamma(n + 1.0) / gamma(n - k + 1.0))
+}
+
+/// `nCr`.
+pub fn choose(n: Value, k: Value) -> Value {
+    if let (Some(n), Some(k)) = (n.to_whole(), k.to_whole())
+        && let Some(result) = exact_choose(&n, &k)
+    {
+        return Value::from(result);
+    }
+    let (n, k) = (n.to_f64(), k.to_f64());
+    Value::Float(gamma(n + 1.0) / (gamma(k + 1.0) * gamma(n - k + 1.0)))
+}
@@ -0,0 +1,37 @@
+//! Physical constants, CODATA 2018 recommended values in SI units.
+//!
+//! They are written with the `phys.` namespace, e.g. `phys.c`, so they never
+//! clash with user variables or with the math constants `pi`, `e` and `tau`.
+
+use crate::dimension::Dimension;
+
+/// Names (short symbol first), value and dimension of each constant.
+#[rustfmt::skip]
+const PHYSICAL: &[(&[&str], f64, Dimension)] = &[
+    (&["c", "speed_of_light"], 299_792_458_f64, Dimension::new(1, 0, -1, 0, 0, 0)),
+    (&["h", "planck"], 6.626_070_15e-34, Dimension::new(2, 1, -1, 0, 0, 0)),
+    (&["hbar", "reduced_planck"], 1.054_571_817e-34, Dimension::new(2, 1, -1, 0, 0, 0)),
+    (&["k", "boltzmann"], 1.380_649e-23, Dimension::new(2, 1, -2, 0, -1, 0)),
+    (&["NA", "avogadro"], 6.022_140_76e23, Dimension::new(0, 0, 0, 0, 0, -1)),
+    (&["e", "elementary_charge"], 1.602_176_634e-19, Dimension::new(0, 0, 1, 1, 0, 0)),
+    (&["g", "gravity"], 9.806_65, Dimension::new(1, 0, -2, 0, 0, 0)),
+    (&["G", "gravitation"], 6.674_30e-11, Dimension::new(3, -1, -2, 0, 0, 0)),
+    (&["R", "gas"], 8.314_462_618, Dimension::new(2, 1, -2, 0, -1, -1)),
+    (&["F", "faraday"], 96_485.332_12, Dimension::new(0, 0, 1, 1, 0, -1)),
+    (&["eps0", "vacuum_permittivity"], 8.854_187_812_8e-12, Dimension::new(-3, -1, 4, 2, 0, 0)),
+    (&["mu0", "vacuum_permeability"], 1.256_637_062_12e-6, Dimension::new(1, 1, -2, -2, 0, 0)),
+    (&["sigma", "stefan_boltzmann"], 5.670_374_419e-8, Dimension::new(0, 1, -3, 0, -4, 0)),
+    (&["me", "electron_mass"], 9.109_383_701_5e-31, Dimension::new(0, 1, 0, 0, 0, 0)),
+    (&["mp", "proton_mass"], 1.672_621_923_69e-27, Dimension::new(0, 1, 0, 0, 0, 0)),
+    (&["u", "atomic_mass"], 1.660_539_066_60e-27, Dimension::new(0, 1, 0, 0, 0, 0)),
+    (&["atm", "standard_atmosphere"], 101_325_f64, Dimension::new(-1, 1, -2, 0, 0, 0)),
+];
+
+/// Looks up a constant by name, without the `phys.` prefix, with its
+/// dimension.
+pub fn physical(name: &str) -> Option<(f64, Dimension)> {
+    PHYSICAL
+        .iter()
+        .find(|(names, _, _)| names.contains(&name))
+        .map(|&(_, value, dimension)| (value, dimension))
+}
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub enum Temperature {
     Kelvin,
@@ -96,6 +98,190 @@ pub enum DigitalInformation {
     Petabyte,
 }
 
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum DataRate {
+    Bit,
+    Kilobit,
+    Megabit,
+    Gigabit,
+    Terabit,
+    Byte,
+    Kilobyte,
+    Megabyte,
+    Gigabyte,
+    Terabyte,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Volume {
+    Millilitre,
+    Litre,
+    CubicCentimetre,
+    CubicMetre,
+    CubicInch,
+    UsGallon,
+    UsQuart,
+    UsPint,
+    UsCup,
+    UsFluidOunce,
+    UsTablespoon,
+    UsTeaspoon,
+    ImperialGallon,
+    ImperialQuart,
+    ImperialPint,
+    ImperialCup,
+    ImperialFluidOunce,
+    ImperialTablespoon,
+    ImperialTeaspoon,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Energy {
+    Joule,
+    Kilojoule,
+    Calorie,
+    Kilocalorie,
+    WattHour,
+    KilowattHour,
+    BritishThermalUnit,
+    Electronvolt,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Power {
+    Watt,
+    Kilowatt,
+    Megawatt,
+    Horsepower,
+    BritishThermalUnitPerHour,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Force {
+    Newton,
+    Kilonewton,
+    Pound,
+    Dyne,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Pressure {
+    Pascal,
+    Kilopascal,
+    Bar,
+    Atmosphere,
+    PoundPerSquareInch,
+    MillimetreOfMercury,
+    Torr,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Frequency {
+    Hertz,
+    Kilohertz,
+    Megahertz,
+    Gigahertz,
+    RevolutionPerMinute,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Voltage {
+    Millivolt,
+    Volt,
+    Kilovolt,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Current {
+    Microampere,
+    Milliampere,
+    Ampere,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Resistance {
+    Milliohm,
+    Ohm,
+    Kilohm,
+    Megohm,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Charge {
+    Coulomb,
+    MilliampereHour,
+    AmpereHour,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Capacitance {
+    Picofarad,
+    Nanofarad,
+    Microfarad,
+    Farad,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum FuelEconomy {
+    KilometresPerLitre,
+    MilesPerUsGallon,
+    MilesPerImperialGallon,
+    LitresPer100Kilometres,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Typography {
+    Pixel,
+    Point,
+    Pica,
+    Em,
+    Rem,
+    Inch,
+    Centimetre,
+}
+
+/// Amount of substance.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Amount {
+    Micromole,
+    Millimole,
+    Mole,
+    Kilomole,
+}
+
+/// Currencies by ISO 4217 code.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum Currency {
+    Usd,
+    Eur,
+    Gbp,
+    Jpy,
+    Chf,
+    Cad,
+    Aud,
+    Nzd,
+    Cny,
+    Hkd,
+    Sgd,
+    Krw,
+    Inr,
+    Mxn,
+    Brl,
+    Sek,
+    Nok,
+    Dkk,
+    Pln,
+    Zar,
+    Try,
+}
+
+impl Currency {
+    /// ISO 4217 code, e.g. `USD`.
+    pub fn code(self) -> String {
+        format!("{:?}", self).to_uppercase()
+    }
+}
+
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub enum UnitType {
     Temperature(Temperature),
@@ -107,10 +293,124 @@ pub enum UnitType {
     Area(Area),
     Speed(Speed),
     Digitalinformation(DigitalInformation),
+    DataRate(DataRate),
+    Volume(Volume),
+    Energy(Energy),
+    Power(Power),
+    Force(Force),
+    Pressure(Pressure),
+    Frequency(Frequency),
+    Voltage(Voltage),
+    Current(Current),
+    Resistance(Resistance),
+    Charge(Charge),
+    Capacitance(Capacitance),
+    FuelEconomy(FuelEconomy),
+    Typography(Typography),
+    Currency(Currency),
+    Amount(Amount),
+}
+
+lazy_static! {
+    /// US dollars per unit of each currency, from the bundled snapshot.
+    static ref RATE_SNAPSHOT: HashMap<Currency, f64> =
+        parse_rates(include_str!("../assets/currency_rates.csv"));
+}
+
+/// Reads a `code,per_usd` table. Lines starting with `#` and unknown codes are skipped.
+fn parse_rates(csv: &str) -> HashMap<Currency, f64> {
+    csv.lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let (code, per_usd) = line.split_once(',')?;
+            let currency = match format!("CURRENCY::{}", code.trim().to_uppercase()).parse() {
+                Ok(UnitType::Currency(currency)) => currency,
+                _ => return None,
+            };
+            let per_usd = per_usd.trim().parse::<f64>().ok()?;
+            Some((currency, 1_f64 / per_usd))
+        })
+        .collect()
+}
+
+/// Sheet settings some units depend on.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Context {
+    /// Pixels per inch.
+    pub dpi: f64,
+    /// Root font size in pixels, used by `em` and `rem`.
+    pub base_font_size: f64,
+    /// US dollars per unit of each currency.
+    pub rates: HashMap<Currency, f64>,
+}
+
+impl Default for Context {
+    fn default() -> Self {
+        Self {
+            dpi: 96_f64,
+            base_font_size: 16_f64,
+            rates: RATE_SNAPSHOT.clone(),
+        }
+    }
+}
+
+impl Context {
+    /// Conversion factor of the units that depend on this context.
+    fn conversion_factor(&self, u: UnitType) -> Option<f64> {
+        match u {
+            UnitType::Typography(Typography::Pixel) => Some(1_f64 / self.dpi),
+            UnitType::Typography(Typography::Em | Typography::Rem) => {
+                Some(self.base_font_size / self.dpi)
+            }
+            // A missing or unusable rate makes the conversion invalid.
+            UnitType::Currency(currency) => Some(
+                self.rates
+                    .get(&currency)
+                    .copied()
+                    .filter(|rate| rate.is_finite() && *rate > 0.0)
+                    .unwrap_or(f64::NAN),
+            ),
+            _ => None,
+        }
+    }
 }
 
 // pub struct InvalidConversion;
 
+/// Decimal degrees from degrees, minutes and seconds of arc.
+/// The sign of `degrees` applies to the whole angle.
+pub fn dms_to_degrees(negative: bool, degrees: f64, minutes: f64, seconds: f64) -> f64 {
+    let value = degrees.abs() + minutes / 60_f64 + seconds / 3600_f64;
+    if negative { -value } else { value }
+}
+
+/// Seconds needed to move `size` of data at `rate`.
+pub fn transfer_time(size: f64, size_unit: UnitType, rate: f64, rate_unit: UnitType) -> f64 {
+    let bits = convert(
+        size,
+        size_unit,
+        UnitType::Digitalinformation(DigitalInformation::Bit),
+    );
+    let bits_per_second = convert(rate, rate_unit, UnitType::DataRate(DataRate::Bit));
+    bits / bits_per_second
+}
+
+/// Like [`convert`], but also handles units that depend on the sheet settings.
+pub fn convert_with(value: f64, from: UnitType, to: UnitType, context: &Context) -> f64 {
+    if std::mem::discriminant(&from) != std::mem::discriminant(&to) {
+        return f64::NAN;
+    }
+    let factor = |u| {
+        context
+            .conversion_factor(u)
+            .map_or_else(|| find_conversion_factor(u), Ok)
+    };
+    match (factor(from), factor(to)) {
+        (Ok(from), Ok(to)) => value * from / to,
+        _ => convert(value, from, to),
+    }
+}
+
 pub fn convert(value: f64, from: UnitType, to: UnitType) -> f64 {
     if from == to {
         return value;
@@ -120,7 +420,7 @@ pub fn convert(value: f64, from: UnitType, to: UnitType) -> f64 {
     }
     match (find_conversion_factor(from), find_conversion_factor(to)) {
         (Ok(from), Ok(to)) => value * from / to,
-        (Err(from), Err(to)) => match (from, to) {
+        (Err(UnitType::Temperature(from)), Err(UnitType::Temperature(to))) => match (from, to) {
             (Temperature::Kelvin, Temperature::Celsius) => value - 273.15,
             (Temperature::Kelvin, Temperature::Fahrenheit) => value.mul_add(1.8, -459.67),
             (Temperature::Kelvin, Temperature::Kelvin) => value,
@@ -133,15 +433,40 @@ pub fn convert(value: f64, from: UnitType, to: UnitType) -> f64 {
             (Temperature::Fahrenheit, Temperature::Fahrenheit) => value,
             (Temperature::Fahrenheit, Temperature::Kelvin) => (value + 459.67) * 5f64 / 9f64,
         },
-        _ => f64::NAN,
+        // At least one side is reciprocal to the base unit of its category.
+        (from, to) => {
+            let base = match from {
+                Ok(factor) => value * factor,
+                Err(unit) => match reciprocal_factor(unit) {
+                    Some(factor) => factor / value,
+                    None => return f64::NAN,
+                },
+            };
+            match to {
+                Ok(factor) => base / factor,
+                Err(unit) => match reciprocal_factor(unit) {
+                    Some(factor) => factor / base,
+                    None => f64::NAN,
+                },
+            }
+        }
+    }
+}
+
+/// Factor `k` of units that are inversely proportional to the base unit of
+/// their category, so that `base = k / value`.
+fn reciprocal_factor(u: UnitType) -> Option<f64> {
+    match u {
+        UnitType::FuelEconomy(FuelEconomy::LitresPer100Kilometres) => Some(100_f64),
+        _ => None,
     }
 }
 
 /// Finds conversion factor if applicable, otherwise return which
 /// actual unit does not have a fixed conversion factor.
-pub fn find_conversion_factor(u: UnitType) -> Result<f64, Temperature> {
+pub fn find_conversion_factor(u: UnitType) -> Result<f64, UnitType> {
     Ok(match u {
-        UnitType::Temperature(v) => return Err(v),
+        UnitType::Temperature(_) => return Err(u),
         UnitType::Acceleration(v) => match v {
             Acceleration::MetrePerSecondSquared => 1_f64,
         },
@@ -149,8 +474,8 @@ pub fn find_conversion_factor(u: UnitType) -> Result<f64, Temperature> {
             // 6.28318531,
             Angle::Turn => std::f64::consts::TAU,
             Angle::Radian => 1_f64,
-            Angle::Degree => 0.0174532925,
-            Angle::Gradian => 0.015707963267949,
+            Angle::Degree => std::f64::consts::PI / 180_f64,
+            Angle::Gradian => std::f64::consts::PI / 200_f64,
         },
         UnitType::Length(v) => match v {
             Length::Millimetre => 0.001,
@@ -221,6 +546,130 @@ pub fn find_conversion_factor(u: UnitType) -> Result<f64, Temperature> {
             DigitalInformation::Petabit => 1.374e+11,
             DigitalInformation::Petabyte => 1.1e+12,
         },
+        UnitType::DataRate(v) => match v {
+            DataRate::Bit => 1_f64,
+            DataRate::Kilobit => 1e3,
+            DataRate::Megabit => 1e6,
+            DataRate::Gigabit => 1e9,
+            DataRate::Terabit => 1e12,
+            DataRate::Byte => 8_f64,
+            DataRate::Kilobyte => 8e3,
+            DataRate::Megabyte => 8e6,
+            DataRate::Gigabyte => 8e9,
+            DataRate::Terabyte => 8e12,
+        },
+        UnitType::Volume(v) => match v {
+            Volume::Millilitre => 1e-6,
+            Volume::Litre => 0.001,
+            Volume::CubicCentimetre => 1e-6,
+            Volume::CubicMetre => 1_f64,
+            Volume::CubicInch => 1.6387064e-5,
+            Volume::UsGallon => 3.785411784e-3,
+            Volume::UsQuart => 9.46352946e-4,
+            Volume::UsPint => 4.73176473e-4,
+            Volume::UsCup => 2.365882365e-4,
+            Volume::UsFluidOunce => 2.95735295625e-5,
+            Volume::UsTablespoon => 1.478676478125e-5,
+            Volume::UsTeaspoon => 4.92892159375e-6,
+            Volume::ImperialGallon => 4.54609e-3,
+            Volume::ImperialQuart => 1.1365225e-3,
+            Volume::ImperialPint => 5.6826125e-4,
+            Volume::ImperialCup => 2.84130625e-4,
+            Volume::ImperialFluidOunce => 2.84130625e-5,
+            Volume::ImperialTablespoon => 1.77581640625e-5,
+            Volume::ImperialTeaspoon => 5.91938802083e-6,
+        },
+        UnitType::Energy(v) => match v {
+            Energy::Joule => 1_f64,
+            Energy::Kilojoule => 1000_f64,
+            // Thermochemical calorie.
+            Energy::Calorie => 4.184,
+            Energy::Kilocalorie => 4184_f64,
+            Energy::WattHour => 3600_f64,
+            Energy::KilowattHour => 3.6e6,
+            // International Table BTU.
+            Energy::BritishThermalUnit => 1055.05585262,
+            Energy::Electronvolt => 1.602176634e-19,
+        },
+        UnitType::Power(v) => match v {
+            Power::Watt => 1_f64,
+            Power::Kilowatt => 1000_f64,
+            Power::Megawatt => 1e6,
+            // Mechanical horsepower.
+            Power::Horsepower => 745.69987158227,
+            Power::BritishThermalUnitPerHour => 0.29307107017,
+        },
+        UnitType::Force(v) => match v {
+            Force::Newton => 1_f64,
+            Force::Kilonewton => 1000_f64,
+            Force::Pound => 4.4482216152605,
+            Force::Dyne => 1e-5,
+        },
+        UnitType::Pressure(v) => match v {
+            Pressure::Pascal => 1_f64,
+            Pressure::Kilopascal => 1000_f64,
+            Pressure::Bar => 100000_f64,
+            Pressure::Atmosphere => 101325_f64,
+            Pressure::PoundPerSquareInch => 6894.757293168,
+            Pressure::MillimetreOfMercury => 133.322387415,
+            Pressure::Torr => 101325_f64 / 760_f64,
+        },
+        UnitType::Frequency(v) => match v {
+            Frequency::Hertz => 1_f64,
+            Frequency::Kilohertz => 1e3,
+            Frequency::Megahertz => 1e6,
+            Frequency::Gigahertz => 1e9,
+            Frequency::RevolutionPerMinute => 1_f64 / 60_f64,
+        },
+        UnitType::Voltage(v) => match v {
+            Voltage::Millivolt => 0.001,
+            Voltage::Volt => 1_f64,
+            Voltage::Kilovolt => 1000_f64,
+        },
+        UnitType::Current(v) => match v {
+            Current::Microampere => 1e-6,
+            Current::Milliampere => 0.001,
+            Current::Ampere => 1_f64,
+        },
+        UnitType::Resistance(v) => match v {
+            Resistance::Milliohm => 0.001,
+            Resistance::Ohm => 1_f64,
+            Resistance::Kilohm => 1000_f64,
+            Resistance::Megohm => 1e6,
+        },
+        UnitType::Charge(v) => match v {
+            Charge::Coulomb => 1_f64,
+            Charge::MilliampereHour => 3.6,
+            Charge::AmpereHour => 3600_f64,
+        },
+        UnitType::Capacitance(v) => match v {
+            Capacitance::Picofarad => 1e-12,
+            Capacitance::Nanofarad => 1e-9,
+            Capacitance::Microfarad => 1e-6,
+            Capacitance::Farad => 1_f64,
+        },
+        UnitType::FuelEconomy(v) => match v {
+            FuelEconomy::KilometresPerLitre => 1_f64,
+            FuelEconomy::MilesPerUsGallon => 0.425143707,
+            FuelEconomy::MilesPerImperialGallon => 0.354006189,
+            FuelEconomy::LitresPer100Kilometres => return Err(u),
+        },
+        // Exchange rates depend on the sheet, see `Context`.
+        UnitType::Currency(_) => return Err(u),
+        UnitType::Amount(v) => match v {
+            Amount::Micromole => 1e-6_f64,
+            Amount::Millimole => 1e-3_f64,
+            Amount::Mole => 1_f64,
+            Amount::Kilomole => 1e3_f64,
+        },
+        // Inches per unit. Pixels and ems depend on the sheet, see `Context`.
+        UnitType::Typography(v) => match v {
+            Typography::Pixel | Typography::Em | Typography::Rem => return Err(u),
+            Typography::Point => 1_f64 / 72_f64,
+            Typography::Pica => 1_f64 / 6_f64,
+            Typography::Inch => 1_f64,
+            Typography::Centimetre => 1_f64 / 2.54,
+        },
     })
 }
 
@@ -330,6 +779,150 @@ impl std::str::FromStr for UnitType {
                 Ok(UnitType::Digitalinformation(DigitalInformation::Petabyte))
             }
 
+            // DataRate
+            "DATARATE::BIT_PER_SECOND" => Ok(UnitType::DataRate(DataRate::Bit)),
+            "DATARATE::KILOBIT_PER_SECOND" => Ok(UnitType::DataRate(DataRate::Kilobit)),
+            "DATARATE::MEGABIT_PER_SECOND" => Ok(UnitType::DataRate(DataRate::Megabit)),
+            "DATARATE::GIGABIT_PER_SECOND" => Ok(UnitType::DataRate(DataRate::Gigabit)),
+            "DATARATE::TERABIT_PER_SECOND" => Ok(UnitType::DataRate(DataRate::Terabit)),
+            "DATARATE::BYTE_PER_SECOND" => Ok(UnitType::DataRate(DataRate::Byte)),
+            "DATARATE::KILOBYTE_PER_SECOND" => Ok(UnitType::DataRate(DataRate::Kilobyte)),
+            "DATARATE::MEGABYTE_PER_SECOND" => Ok(UnitType::DataRate(DataRate::Megabyte)),
+            "DATARATE::GIGABYTE_PER_SECOND" => Ok(UnitType::DataRate(DataRate::Gigabyte)),
+            "DATARATE::TERABYTE_PER_SECOND" => Ok(UnitType::DataRate(DataRate::Terabyte)),
+
+            // Volume
+            "VOLUME::MILLILITRE" => Ok(UnitType::Volume(Volume::Millilitre)),
+            "VOLUME::LITRE" => Ok(UnitType::Volume(Volume::Litre)),
+            "VOLUME::CUBIC_CENTIMETRE" => Ok(UnitType::Volume(Volume::CubicCentimetre)),
+            "VOLUME::CUBIC_METRE" => Ok(UnitType::Volume(Volume::CubicMetre)),
+            "VOLUME::CUBIC_INCH" => Ok(UnitType::Volume(Volume::CubicInch)),
+            "VOLUME::US_GALLON" => Ok(UnitType::Volume(Volume::UsGallon)),
+            "VOLUME::US_QUART" => Ok(UnitType::Volume(Volume::UsQuart)),
+            "VOLUME::US_PINT" => Ok(UnitType::Volume(Volume::UsPint)),
+            "VOLUME::US_CUP" => Ok(UnitType::Volume(Volume::UsCup)),
+            "VOLUME::US_FLUID_OUNCE" => Ok(UnitType::Volume(Volume::UsFluidOunce)),
+            "VOLUME::US_TABLESPOON" => Ok(UnitType::Volume(Volume::UsTablespoon)),
+            "VOLUME::US_TEASPOON" => Ok(UnitType::Volume(Volume::UsTeaspoon)),
+            "VOLUME::IMPERIAL_GALLON" => Ok(UnitType::Volume(Volume::ImperialGallon)),
+            "VOLUME::IMPERIAL_QUART" => Ok(UnitType::Volume(Volume::ImperialQuart)),
+            "VOLUME::IMPERIAL_PINT" => Ok(UnitType::Volume(Volume::ImperialPint)),
+            "VOLUME::IMPERIAL_CUP" => Ok(UnitType::Volume(Volume::ImperialCup)),
+            "VOLUME::IMPERIAL_FLUID_OUNCE" => Ok(UnitType::Volume(Volume::ImperialFluidOunce)),
+            "VOLUME::IMPERIAL_TABLESPOON" => Ok(UnitType::Volume(Volume::ImperialTablespoon)),
+            "VOLUME::IMPERIAL_TEASPOON" => Ok(UnitType::Volume(Volume::ImperialTeaspoon)),
+
+            // Energy
+            "ENERGY::JOULE" => Ok(UnitType::Energy(Energy::Joule)),
+            "ENERGY::KILOJOULE" => Ok(UnitType::Energy(Energy::Kilojoule)),
+            "ENERGY::CALORIE" => Ok(UnitType::Energy(Energy::Calorie)),
+            "ENERGY::KILOCALORIE" => Ok(UnitType::Energy(Energy::Kilocalorie)),
+            "ENERGY::WATT_HOUR" => Ok(UnitType::Energy(Energy::WattHour)),
+            "ENERGY::KILOWATT_HOUR" => Ok(UnitType::Energy(Energy::KilowattHour)),
+            "ENERGY::BTU" => Ok(UnitType::Energy(Energy::BritishThermalUnit)),
+            "ENERGY::ELECTRONVOLT" => Ok(UnitType::Energy(Energy::Electronvolt)),
+
+            // Power
+            "POWER::WATT" => Ok(UnitType::Power(Power::Watt)),
+            "POWER::KILOWATT" => Ok(UnitType::Power(Power::Kilowatt)),
+            "POWER::MEGAWATT" => Ok(UnitType::Power(Power::Megawatt)),
+            "POWER::HORSEPOWER" => Ok(UnitType::Power(Power::Horsepower)),
+            "POWER::BTU_PER_HOUR" => Ok(UnitType::Power(Power::BritishThermalUnitPerHour)),
+
+            // Force
+            "FORCE::NEWTON" => Ok(UnitType::Force(Force::Newton)),
+            "FORCE::KILONEWTON" => Ok(UnitType::Force(Force::Kilonewton)),
+            "FORCE::POUND_FORCE" => Ok(UnitType::Force(Force::Pound)),
+            "FORCE::DYNE" => Ok(UnitType::Force(Force::Dyne)),
+
+            // Pressure
+            "PRESSURE::PASCAL" => Ok(UnitType::Pressure(Pressure::Pascal)),
+            "PRESSURE::KILOPASCAL" => Ok(UnitType::Pressure(Pressure::Kilopascal)),
+            "PRESSURE::BAR" => Ok(UnitType::Pressure(Pressure::Bar)),
+            "PRESSURE::ATMOSPHERE" => Ok(UnitType::Pressure(Pressure::Atmosphere)),
+            "PRESSURE::PSI" => Ok(UnitType::Pressure(Pressure::PoundPerSquareInch)),
+            "PRESSURE::MILLIMETRE_OF_MERCURY" => {
+                Ok(UnitType::Pressure(Pressure::MillimetreOfMercury))
+            }
+            "PRESSURE::TORR" => Ok(UnitType::Pressure(Pressure::Torr)),
+
+            // Frequency
+            "FREQUENCY::HERTZ" => Ok(UnitType::Frequency(Frequency::Hertz)),
+            "FREQUENCY::KILOHERTZ" => Ok(UnitType::Frequency(Frequency::Kilohertz)),
+            "FREQUENCY::MEGAHERTZ" => Ok(UnitType::Frequency(Frequency::Megahertz)),
+            "FREQUENCY::GIGAHERTZ" => Ok(UnitType::Frequency(Frequency::Gigahertz)),
+            "FREQUENCY::RPM" => Ok(UnitType::Frequency(Frequency::RevolutionPerMinute)),
+
+            // Electrical
+            "VOLTAGE::MILLIVOLT" => Ok(UnitType::Voltage(Voltage::Millivolt)),
+            "VOLTAGE::VOLT" => Ok(UnitType::Voltage(Voltage::Volt)),
+            "VOLTAGE::KILOVOLT" => Ok(UnitType::Voltage(Voltage::Kilovolt)),
+            "CURRENT::MICROAMPERE" => Ok(UnitType::Current(Current::Microampere)),
+            "CURRENT::MILLIAMPERE" => Ok(UnitType::Current(Current::Milliampere)),
+            "CURRENT::AMPERE" => Ok(UnitType::Current(Current::Ampere)),
+            "RESISTANCE::MILLIOHM" => Ok(UnitType::Resistance(Resistance::Milliohm)),
+            "RESISTANCE::OHM" => Ok(UnitType::Resistance(Resistance::Ohm)),
+            "RESISTANCE::KILOHM" => Ok(UnitType::Resistance(Resistance::Kilohm)),
+            "RESISTANCE::MEGOHM" => Ok(UnitType::Resistance(Resistance::Megohm)),
+            "CHARGE::COULOMB" => Ok(UnitType::Charge(Charge::Coulomb)),
+            "CHARGE::MILLIAMPERE_HOUR" => Ok(UnitType::Charge(Charge::MilliampereHour)),
+            "CHARGE::AMPERE_HOUR" => Ok(UnitType::Charge(Charge::AmpereHour)),
+            "CAPACITANCE::PICOFARAD" => Ok(UnitType::Capacitance(Capacitance::Picofarad)),
+            "CAPACITANCE::NANOFARAD" => Ok(UnitType::Capacitance(Capacitance::Nanofarad)),
+            "CAPACITANCE::MICROFARAD" => Ok(UnitType::Capacitance(Capacitance::Microfarad)),
+            "CAPACITANCE::FARAD" => Ok(UnitType::Capacitance(Capacitance::Farad)),
+
+            // FuelEconomy
+            "FUELECONOMY::KILOMETRES_PER_LITRE" => {
+                Ok(UnitType::FuelEconomy(FuelEconomy::KilometresPerLitre))
+            }
+            "FUELECONOMY::MILES_PER_US_GALLON" => {
+                Ok(UnitType::FuelEconomy(FuelEconomy::MilesPerUsGallon))
+            }
+            "FUELECONOMY::MILES_PER_IMPERIAL_GALLON" => {
+                Ok(UnitType::FuelEconomy(FuelEconomy::MilesPerImperialGallon))
+            }
+            "FUELECONOMY::LITRES_PER_100_KILOMETRES" => {
+                Ok(UnitType::FuelEconomy(FuelEconomy::LitresPer100Kilometres))
+            }
+
+            // Typography
+            "TYPOGRAPHY::PIXEL" => Ok(UnitType::Typography(Typography::Pixel)),
+            "TYPOGRAPHY::POINT" => Ok(UnitType::Typography(Typography::Point)),
+            "TYPOGRAPHY::PICA" => Ok(UnitType::Typography(Typography::Pica)),
+            "TYPOGRAPHY::EM" => Ok(UnitType::Typography(Typography::Em)),
+            "TYPOGRAPHY::REM" => Ok(UnitType::Typography(Typography::Rem)),
+            "TYPOGRAPHY::INCH" => Ok(UnitType::Typography(Typography::Inch)),
+            "TYPOGRAPHY::CENTIMETRE" => Ok(UnitType::Typography(Typography::Centimetre)),
+
+            // Amount
+            "AMOUNT::MICROMOLE" => Ok(UnitType::Amount(Amount::Micromole)),
+            "AMOUNT::MILLIMOLE" => Ok(UnitType::Amount(Amount::Millimole)),
+            "AMOUNT::MOLE" => Ok(UnitType::Amount(Amount::Mole)),
+            "AMOUNT::KILOMOLE" => Ok(UnitType::Amount(Amount::Kilomole)),
+            // Currency
+            "CURRENCY::USD" => Ok(UnitType::Currency(Currency::Usd)),
+            "CURRENCY::EUR" => Ok(UnitType::Currency(Currency::Eur)),
+            "CURRENCY::GBP" => Ok(UnitType::Currency(Currency::Gbp)),
+            "CURRENCY::JPY" => Ok(UnitType::Currency(Currency::Jpy)),
+            "CURRENCY::CHF" => Ok(UnitType::Currency(Currency::Chf)),
+            "CURRENCY::CAD" => Ok(UnitType::Currency(Currency::Cad)),
+            "CURRENCY::AUD" => Ok(UnitType::Currency(Currency::Aud)),
+            "CURRENCY::NZD" => Ok(UnitType::Currency(Currency::Nzd)),
+            "CURRENCY::CNY" => Ok(UnitType::Currency(Currency::Cny)),
+            "CURRENCY::HKD" => Ok(UnitType::Currency(Currency::Hkd)),
+            "CURRENCY::SGD" => Ok(UnitType::Currency(Currency::Sgd)),
+            "CURRENCY::KRW" => Ok(UnitType::Currency(Currency::Krw)),
+            "CURRENCY::INR" => Ok(UnitType::Currency(Currency::Inr)),
+            "CURRENCY::MXN" => Ok(UnitType::Currency(Currency::Mxn)),
+            "CURRENCY::BRL" => Ok(UnitType::Currency(Currency::Brl)),
+            "CURRENCY::SEK" => Ok(UnitType::Currency(Currency::Sek)),
+            "CURRENCY::NOK" => Ok(UnitType::Currency(Currency::Nok)),
+            "CURRENCY::DKK" => Ok(UnitType::Currency(Currency::Dkk)),
+            "CURRENCY::PLN" => Ok(UnitType::Currency(Currency::Pln)),
+            "CURRENCY::ZAR" => Ok(UnitType::Currency(Currency::Zar)),
+            "CURRENCY::TRY" => Ok(UnitType::Currency(Currency::Try)),
+
             _ => Err(format!("'{}' is not a valid value for UnitType", s)),
         }
     }
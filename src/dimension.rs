@@ -0,0 +1,195 @@
+//! Physical dimensions, as powers of the SI base units.
+
+use std::fmt;
+use std::ops::{Div, Mul};
+
+use crate::convert_chart::{
+    Acceleration, Amount, Area, Capacitance, Charge, Current, Energy, Force, Frequency, Length,
+    Mass, Power, Pressure, Resistance, Speed, Temperature, Time, UnitType, Voltage, Volume,
+};
+
+/// Symbols of the base units, in the order of their powers in a `Dimension`.
+const SYMBOLS: [&str; 6] = ["m", "kg", "s", "A", "K", "mol"];
+
+/// Powers of the metre, kilogram, second, ampere, kelvin and mole.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct Dimension([i8; 6]);
+
+impl Dimension {
+    /// A plain number.
+    pub const NONE: Dimension = Dimension([0; 6]);
+
+    pub const fn new(m: i8, kg: i8, s: i8, a: i8, k: i8, mol: i8) -> Self {
+        Dimension([m, kg, s, a, k, mol])
+    }
+
+    pub fn is_none(self) -> bool {
+        self == Self::NONE
+    }
+
+    /// The dimension raised to the power `n`.
+    pub fn powi(self, n: i8) -> Option<Self> {
+        let mut powers = self.0;
+        for power in &mut powers {
+            *power = power.checked_mul(n)?;
+        }
+        Some(Dimension(powers))
+    }
+
+    /// The dimension whose square is this one, if there is one.
+    pub fn sqrt(self) -> Option<Self> {
+        self.0
+            .iter()
+            .all(|power| power % 2 == 0)
+            .then(|| Dimension(self.0.map(|power| power / 2)))
+    }
+}
+
+impl Mul for Dimension {
+    type Output = Dimension;
+
+    fn mul(self, rhs: Dimension) -> Dimension {
+        Dimension(std::array::from_fn(|i| self.0[i] + rhs.0[i]))
+    }
+}
+
+impl Div for Dimension {
+    type Output = Dimension;
+
+    fn div(self, rhs: Dimension) -> Dimension {
+        Dimension(std::array::from_fn(|i| self.0[i] - rhs.0[i]))
+    }
+}
+
+/// `n` in superscript digits, e.g. `⁻²`.
+fn superscript(n: i8) -> String {
+    const DIGITS: [char; 10] = ['⁰', '¹', '²', '³', '⁴', '⁵', '⁶', '⁷', '⁸', '⁹'];
+    n.to_string()
+        .chars()
+        .map(|c| match c.to_digit(10) {
+            Some(digit) => DIGITS[digit as usize],
+            None => '⁻',
+        })
+        .collect()
+}
+
+/// Writes a product of base units, e.g. `kg·m²`.
+fn product(units: &[(&str, i8)]) -> String {
+    units
+        .iter()
+        .map(|&(symbol, power)| match power {
+            1 => symbol.to_string(),
+            _ => format!("{symbol}{}", superscript(power)),
+        })
+        .collect::<Vec<_>>()
+        .join("·")
+}
+
+impl fmt::Display for Dimension {
+    /// Base units as in `m³/(kg·s²)`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let units = SYMBOLS.iter().zip(self.0);
+        let above: Vec<(&str, i8)> = units
+            .clone()
+            .filter(|&(_, power)| power > 0)
+            .map(|(&symbol, power)| (symbol, power))
+            .collect();
+        let below: Vec<(&str, i8)> = units
+            .filter(|&(_, power)| power < 0)
+            .map(|(&symbol, power)| (symbol, -power))
+            .collect();
+        let above = match above.is_empty() {
+            true => "1".to_string(),
+            false => product(&above),
+        };
+        match below.len() {
+            0 => write!(f, "{above}"),
+            1 => write!(f, "{above}/{}", product(&below)),
+            _ => write!(f, "{above}/({})", product(&below)),
+        }
+    }
+}
+
+/// The dimension of a unit, and the SI unit of the same kind that quantities
+/// are carried in. `None` for units outside the SI, such as bytes or money.
+pub fn of(unit: UnitType) -> Option<(Dimension, UnitType)> {
+    Some(match unit {
+        UnitType::Length(_) => (
+            Dimension::new(1, 0, 0, 0, 0, 0),
+            UnitType::Length(Length::Metre),
+        ),
+        UnitType::Mass(_) => (
+            Dimension::new(0, 1, 0, 0, 0, 0),
+            UnitType::Mass(Mass::Kilogram),
+        ),
+        UnitType::Time(_) => (
+            Dimension::new(0, 0, 1, 0, 0, 0),
+            UnitType::Time(Time::Second),
+        ),
+        UnitType::Current(_) => (
+            Dimension::new(0, 0, 0, 1, 0, 0),
+            UnitType::Current(Current::Ampere),
+        ),
+        UnitType::Temperature(_) => (
+            Dimension::new(0, 0, 0, 0, 1, 0),
+            UnitType::Temperature(Temperature::Kelvin),
+        ),
+        UnitType::Amount(_) => (
+            Dimension::new(0, 0, 0, 0, 0, 1),
+            UnitType::Amount(Amount::Mole),
+        ),
+        UnitType::Area(_) => (
+            Dimension::new(2, 0, 0, 0, 0, 0),
+            UnitType::Area(Area::SquareMetre),
+        ),
+        UnitType::Volume(_) => (
+            Dimension::new(3, 0, 0, 0, 0, 0),
+            UnitType::Volume(Volume::CubicMetre),
+        ),
+        UnitType::Speed(_) => (
+            Dimension::new(1, 0, -1, 0, 0, 0),
+            UnitType::Speed(Speed::MetrePerSecond),
+        ),
+        UnitType::Acceleration(_) => (
+            Dimension::new(1, 0, -2, 0, 0, 0),
+            UnitType::Acceleration(Acceleration::MetrePerSecondSquared),
+        ),
+        UnitType::Frequency(_) => (
+            Dimension::new(0, 0, -1, 0, 0, 0),
+            UnitType::Frequency(Frequency::Hertz),
+        ),
+        UnitType::Force(_) => (
+            Dimension::new(1, 1, -2, 0, 0, 0),
+            UnitType::Force(Force::Newton),
+        ),
+        UnitType::Energy(_) => (
+            Dimension::new(2, 1, -2, 0, 0, 0),
+            UnitType::Energy(Energy::Joule),
+        ),
+        UnitType::Power(_) => (
+            Dimension::new(2, 1, -3, 0, 0, 0),
+            UnitType::Power(Power::Watt),
+        ),
+        UnitType::Pressure(_) => (
+            Dimension::new(-1, 1, -2, 0, 0, 0),
+            UnitType::Pressure(Pressure::Pascal),
+        ),
+        UnitType::Charge(_) => (
+            Dimension::new(0, 0, 1, 1, 0, 0),
+            UnitType::Charge(Charge::Coulomb),
+        ),
+        UnitType::Voltage(_) => (
+            Dimension::new(2, 1, -3, -1, 0, 0),
+            UnitType::Voltage(Voltage::Volt),
+        ),
+        UnitType::Resistance(_) => (
+            Dimension::new(2, 1, -3, -2, 0, 0),
+            UnitType::Resistance(Resistance::Ohm),
+        ),
+        UnitType::Capacitance(_) => (
+            Dimension::new(-2, -1, 4, 2, 0, 0),
+            UnitType::Capacitance(Capacitance::Farad),
+        ),
+        _ => return None,
+    })
+}
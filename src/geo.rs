@@ -0,0 +1,40 @@
+//! Great-circle helpers for points given as latitude/longitude in degrees.
+
+/// Mean Earth radius in metres (IUGG).
+const EARTH_RADIUS: f64 = 6_371_008.8;
+
+/// Whether both points have a latitude within ±90° and a longitude within
+/// ±180°.
+fn valid(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> bool {
+    [lat1, lat2].iter().all(|lat| lat.abs() <= 90_f64)
+        && [lon1, lon2].iter().all(|lon| lon.abs() <= 180_f64)
+}
+
+/// Great-circle distance in metres, using the haversine formula. NaN for
+/// points that are out of range.
+pub fn distance(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    if !valid(lat1, lon1, lat2, lon2) {
+        return f64::NAN;
+    }
+    let (phi1, phi2) = (lat1.to_radians(), lat2.to_radians());
+    let delta_phi = (lat2 - lat1).to_radians();
+    let delta_lambda = (lon2 - lon1).to_radians();
+
+    let a = (delta_phi / 2_f64).sin().powi(2)
+        + phi1.cos() * phi2.cos() * (delta_lambda / 2_f64).sin().powi(2);
+    2_f64 * EARTH_RADIUS * a.sqrt().atan2((1_f64 - a).sqrt())
+}
+
+/// Initial bearing in radians from the first point towards the second,
+/// clockwise from north in `[0, 2π)`. NaN for points that are out of range.
+pub fn bearing(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    if !valid(lat1, lon1, lat2, lon2) {
+        return f64::NAN;
+    }
+    let (phi1, phi2) = (lat1.to_radians(), lat2.to_radians());
+    let delta_lambda = (lon2 - lon1).to_radians();
+
+    let y = delta_lambda.sin() * phi2.cos();
+    let x = phi1.cos() * phi2.sin() - phi1.sin() * phi2.cos() * delta_lambda.cos();
+    y.atan2(x).rem_euclid(std::f64::consts::TAU)
+}
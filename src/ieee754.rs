@@ -0,0 +1,94 @@
+//! Inspection of IEEE-754 binary floating point values.
+
+/// Bit fields, exact value and ULP gaps of a double.
+pub fn describe_f64(value: f64) -> String {
+    let gaps = (value - value.next_down(), value.next_up() - value);
+    describe(
+        value.to_bits(),
+        11,
+        52,
+        format!("{:?} / +{:?}", gaps.0, gaps.1),
+    )
+}
+
+/// Bit fields, exact value and ULP gaps of `value` rounded to a single.
+pub fn describe_f32(value: f32) -> String {
+    let gaps = (value - value.next_down(), value.next_up() - value);
+    describe(
+        value.to_bits() as u64,
+        8,
+        23,
+        format!("{:?} / +{:?}", gaps.0, gaps.1),
+    )
+}
+
+fn describe(bits: u64, exponent_bits: u32, mantissa_bits: u32, gaps: String) -> String {
+    let sign = bits >> (exponent_bits + mantissa_bits);
+    let exponent = (bits >> mantissa_bits) & ((1 << exponent_bits) - 1);
+    let fraction = bits & ((1 << mantissa_bits) - 1);
+    let bias = (1_i32 << (exponent_bits - 1)) - 1;
+    let fields = format!(
+        "sign {sign} · exponent {exponent:0ew$b} · mantissa {fraction:0mw$b}",
+        ew = exponent_bits as usize,
+        mw = mantissa_bits as usize,
+    );
+
+    let sign = if sign == 1 { "-" } else { "" };
+    if exponent == (1 << exponent_bits) - 1 {
+        let special = if fraction == 0 { "inf" } else { "NaN" };
+        return format!("{fields} · {sign}{special}");
+    }
+
+    // value = significand × 2^power
+    let (significand, power, scale) = match exponent {
+        0 => (fraction, 1 - bias - mantissa_bits as i32, 1 - bias),
+        _ => (
+            fraction | (1 << mantissa_bits),
+            exponent as i32 - bias - mantissa_bits as i32,
+            exponent as i32 - bias,
+        ),
+    };
+    format!(
+        "{fields} (2^{scale}) · exact {sign}{} · ulp -{gaps}",
+        exact_decimal(significand, power)
+    )
+}
+
+/// Exact decimal expansion of `significand × 2^power`.
+fn exact_decimal(significand: u64, power: i32) -> String {
+    // Little-endian decimal digits.
+    let mut digits: Vec<u8> = significand
+        .to_string()
+        .bytes()
+        .rev()
+        .map(|b| b - b'0')
+        .collect();
+    // 2^-n = 5^n / 10^n, so negative powers multiply by five and shift the point.
+    let factor = if power < 0 { 5 } else { 2 };
+    for _ in 0..power.unsigned_abs() {
+        let mut carry = 0;
+        for digit in digits.iter_mut() {
+            let product = *digit * factor + carry;
+            *digit = product % 10;
+            carry = product / 10;
+        }
+        if carry > 0 {
+            digits.push(carry);
+        }
+    }
+
+    let point = if power < 0 {
+        power.unsigned_abs() as usize
+    } else {
+        0
+    };
+    while digits.len() <= point {
+        digits.push(0);
+    }
+    let mut text: String = digits.iter().rev().map(|d| (b'0' + d) as char).collect();
+    if point > 0 {
+        text.insert(text.len() - point, '.');
+        text = text.trim_end_matches('0').trim_end_matches('.').to_string();
+    }
+    text
+}
@@ -2,25 +2,48 @@
 extern crate lazy_static;
 extern crate pest;
 
+use bigdecimal::BigDecimal;
 use iced::widget::{column, container, row, scrollable, text, text_editor};
 pub use iced::window::{Id, Position, Settings, close, gain_focus, open};
 use iced::{Alignment, Element, Length, Theme};
-
+use num_bigint::BigInt;
+use num_complex::Complex64;
+use num_rational::BigRational;
+use num_traits::{FromPrimitive, Signed};
+
+mod chemistry;
+mod combinatorics;
+mod constants;
 mod convert_chart;
+mod dimension;
 mod float_pretty_print;
+mod geo;
+mod ieee754;
+mod matrix;
+mod number_theory;
 mod parser;
+mod programmer;
+mod statistics;
 mod test;
+mod value;
 
 struct Formatter;
 
-impl Formatter {
-    fn number(value: f64) -> String {
+/// Numbers `Formatter::number` can print.
+trait Number {
+    fn format(&self) -> String;
+}
+
+impl Number for f64 {
+    fn format(&self) -> String {
         use float_pretty_print::PrettyPrintFloat;
 
-        if value.is_nan() {
+        if self.is_nan() {
             return "-".to_string();
         }
 
+        // Drop conversion noise, such as the 1000.0000000000001 of `1 l to ml`.
+        let value = Formatter::whole(*self, self.abs() * 1e-15);
         if value.fract() == 0.0 {
             return value.to_string().trim().to_string();
         }
@@ -29,6 +52,376 @@ impl Formatter {
     }
 }
 
+impl Number for BigInt {
+    fn format(&self) -> String {
+        self.to_string()
+    }
+}
+
+/// Decimals whose exponent is further from zero than this are written in
+/// scientific notation, rather than as a long run of zeros.
+const MAX_PLAIN_EXPONENT: i64 = 64;
+
+impl Number for BigDecimal {
+    fn format(&self) -> String {
+        let normalized = self.normalized();
+        let (digits, scale) = normalized.as_bigint_and_scale();
+        let exponent = digits.to_string().trim_start_matches('-').len() as i64 - scale - 1;
+        match exponent.abs() > MAX_PLAIN_EXPONENT {
+            true => normalized.to_scientific_notation(),
+            false => normalized.to_plain_string(),
+        }
+    }
+}
+
+impl Formatter {
+    fn number(value: impl Number) -> String {
+        value.format()
+    }
+
+    fn answer(answer: &parser::Answer) -> String {
+        match &answer.exact {
+            Some(parser::Exact::Integer(integer)) => {
+                return Self::integer(*integer, answer.notation);
+            }
+            Some(parser::Exact::Rational(rational))
+                if answer.notation == parser::Notation::Factors =>
+            {
+                return match rational.is_integer() {
+                    true => Self::factors(&rational.to_integer()),
+                    false => "-".to_string(),
+                };
+            }
+            Some(parser::Exact::Rational(rational)) => match answer.notation {
+                parser::Notation::Fraction => return Self::fraction(rational, false),
+                parser::Notation::MixedFraction => return Self::fraction(rational, true),
+                // Integers are printed exactly, however large.
+                parser::Notation::Decimal if rational.is_integer() => {
+                    return Self::number(rational.to_integer());
+                }
+                parser::Notation::Grouped if rational.is_integer() => {
+                    return Self::grouped(Self::number(rational.to_integer()));
+                }
+                parser::Notation::Radix(radix) if rational.is_integer() => {
+                    return Self::radix(&rational.to_integer(), radix);
+                }
+                _ => {}
+            },
+            Some(parser::Exact::Complex(complex)) => {
+                return match answer.notation {
+                    parser::Notation::Polar(unit) => Self::polar(*complex, unit),
+                    _ => Self::complex(*complex),
+                };
+            }
+            Some(parser::Exact::List(items)) => {
+                let items: Vec<String> = items
+                    .iter()
+                    .map(|item| Self::answer(&parser::Answer::new(item.clone(), answer.notation)))
+                    .collect();
+                return format!("[{}]", items.join(", "));
+            }
+            Some(parser::Exact::Error(message)) => return message.clone(),
+            Some(parser::Exact::Decimal(decimal)) => {
+                if let Some(text) = Self::decimal(decimal.as_ref(), answer.notation) {
+                    return text;
+                }
+            }
+            None => {}
+        }
+
+        match answer.notation {
+            parser::Notation::Decimal => Self::number(answer.value),
+            parser::Notation::Dms => Self::dms(answer.value),
+            // Grouping is for positional digits, so large values aren't
+            // written with an exponent.
+            parser::Notation::Grouped => match Self::number(answer.value) {
+                text if text.contains('e') && answer.value.is_finite() => {
+                    Self::grouped(answer.value.to_string())
+                }
+                text => Self::grouped(text),
+            },
+            parser::Notation::Rectangular => Self::number(answer.value),
+            parser::Notation::Factors => match BigInt::from_f64(answer.value) {
+                Some(integer) if answer.value.fract() == 0.0 => Self::factors(&integer),
+                _ => "-".to_string(),
+            },
+            parser::Notation::Polar(unit) => Self::polar(Complex64::from(answer.value), unit),
+            parser::Notation::Radix(radix) => match BigInt::from_f64(answer.value) {
+                Some(integer) if answer.value.fract() == 0.0 => Self::radix(&integer, radix),
+                _ => "-".to_string(),
+            },
+            parser::Notation::Ieee754(bits) => Self::ieee754(answer.value, bits),
+            parser::Notation::Currency(currency) => Self::money(answer.value, currency),
+            parser::Notation::Quantity(dimension) => Self::quantity(answer.value, dimension),
+            parser::Notation::Fraction | parser::Notation::MixedFraction => {
+                match value::fraction(answer.value) {
+                    Some(rational) => Self::fraction(
+                        &rational,
+                        answer.notation == parser::Notation::MixedFraction,
+                    ),
+                    None => "-".to_string(),
+                }
+            }
+        }
+    }
+
+    /// Quantities in SI base units, e.g. `9.80665 m/s²`.
+    fn quantity(value: impl Number, dimension: dimension::Dimension) -> String {
+        match Self::number(value) {
+            number if number == "-" => number,
+            number => format!("{number} {dimension}"),
+        }
+    }
+
+    /// Complex numbers as `2 - 3i`.
+    fn complex(z: Complex64) -> String {
+        if z.is_nan() {
+            return "-".to_string();
+        }
+
+        // Drop rounding noise, such as the real part of `polar(1, 90)` or the
+        // 4.999… imaginary part of `10∠30°`.
+        let noise = z.norm() * 1e-15;
+        let (re, im) = (Self::whole(z.re, noise), Self::whole(z.im, noise));
+        if im == 0.0 {
+            return Self::number(re);
+        }
+
+        let imaginary = match im.abs() {
+            1.0 => "i".to_string(),
+            magnitude => format!("{}i", Self::number(magnitude)),
+        };
+        match (re, im < 0.0) {
+            (0.0, true) => format!("-{imaginary}"),
+            (0.0, false) => imaginary,
+            (re, true) => format!("{} - {imaginary}", Self::number(re)),
+            (re, false) => format!("{} + {imaginary}", Self::number(re)),
+        }
+    }
+
+    /// Complex numbers as `5 ∠ 53.13°`, with the angle in `unit`.
+    fn polar(z: Complex64, unit: convert_chart::Angle) -> String {
+        if z.is_nan() {
+            return "-".to_string();
+        }
+
+        let (magnitude, angle) = z.to_polar();
+        let angle = convert_chart::convert(
+            angle,
+            convert_chart::UnitType::Angle(convert_chart::Angle::Radian),
+            convert_chart::UnitType::Angle(unit),
+        );
+        let symbol = match unit {
+            convert_chart::Angle::Degree => "°",
+            convert_chart::Angle::Radian => " rad",
+            convert_chart::Angle::Gradian => " grad",
+            convert_chart::Angle::Turn => " tr",
+        };
+        format!(
+            "{} ∠ {}{symbol}",
+            Self::number(Self::whole(magnitude, magnitude * 1e-15)),
+            Self::number(Self::whole(angle, angle.abs() * 1e-15))
+        )
+    }
+
+    /// The whole number nearest `x` if they differ by less than `noise`, or
+    /// else `x`.
+    fn whole(x: f64, noise: f64) -> f64 {
+        match x.round() {
+            whole if (x - whole).abs() < noise => whole,
+            _ => x,
+        }
+    }
+
+    /// Fractions as `7/4`, or as `1 3/4` when `mixed`.
+    fn fraction(rational: &BigRational, mixed: bool) -> String {
+        if rational.is_integer() {
+            return rational.numer().to_string();
+        }
+        if !mixed || rational.numer().magnitude() < rational.denom().magnitude() {
+            return format!("{}/{}", rational.numer(), rational.denom());
+        }
+
+        let whole = rational.trunc();
+        let rest = (rational - &whole).abs();
+        format!("{} {}/{}", whole.numer(), rest.numer(), rest.denom())
+    }
+
+    /// Decimal mode results, or `None` for notations only shown in floating point.
+    fn decimal(decimal: Option<&BigDecimal>, notation: parser::Notation) -> Option<String> {
+        let Some(decimal) = decimal else {
+            return Some("-".to_string());
+        };
+        match notation {
+            parser::Notation::Decimal => Some(Self::number(decimal.clone())),
+            parser::Notation::Grouped => Some(Self::grouped(Self::number(decimal.clone()))),
+            parser::Notation::Quantity(dimension) => {
+                Some(Self::quantity(decimal.clone(), dimension))
+            }
+            parser::Notation::Factors if decimal.is_integer() => {
+                let (integer, _) = decimal.with_scale(0).into_bigint_and_exponent();
+                Some(Self::factors(&integer))
+            }
+            parser::Notation::Factors => Some("-".to_string()),
+            // Already rounded to the cent.
+            parser::Notation::Currency(currency) => {
+                Some(format!("{} {}", decimal.to_plain_string(), currency.code()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Programmer mode results. Other bases show the two's-complement bit
+    /// pattern, with binary and hex padded to the width of the type.
+    fn integer(
+        integer: Result<programmer::Int, programmer::IntError>,
+        notation: parser::Notation,
+    ) -> String {
+        match (integer, notation) {
+            (Err(programmer::IntError::Unsupported), _) => "-".to_string(),
+            (Err(error), _) => error.to_string(),
+            (Ok(int), parser::Notation::Radix(radix)) => {
+                let width = match radix {
+                    2 => int.ty().bits as usize,
+                    16 => int.ty().bits as usize / 4,
+                    _ => 0,
+                };
+                Self::digits(int.bits(), radix, width)
+            }
+            (Ok(int), parser::Notation::Grouped) => Self::grouped(int.to_string()),
+            (Ok(int), parser::Notation::Factors) => Self::factors(&int.to_bigint()),
+            (Ok(int), _) => int.to_string(),
+        }
+    }
+
+    /// Prime factorisations as `2^3 × 3`.
+    fn factors(value: &BigInt) -> String {
+        if value.magnitude().bits() <= 1 {
+            return value.to_string();
+        }
+        let Some(factors) = number_theory::factor(value) else {
+            return "-".to_string();
+        };
+        let factors: Vec<String> = factors
+            .into_iter()
+            .map(|factor| match factor {
+                number_theory::Factor::Prime(prime, 1) => prime.to_string(),
+                number_theory::Factor::Prime(prime, power) => format!("{prime}^{power}"),
+                number_theory::Factor::Unfactored(n) => format!("{n} (unfactored)"),
+            })
+            .collect();
+        let sign = if value.is_negative() { "-1 × " } else { "" };
+        format!("{sign}{}", factors.join(" × "))
+    }
+
+    /// Integers in base 2 to 36.
+    fn radix(value: &BigInt, radix: u32) -> String {
+        if !(2..=36).contains(&radix) {
+            return "-".to_string();
+        }
+
+        let sign = if value.is_negative() { "-" } else { "" };
+        let digits = value.magnitude().to_str_radix(radix).to_uppercase();
+        format!("{sign}{}{digits}", Self::prefix(radix))
+    }
+
+    /// The prefix literals in base 2, 8 and 16 are written with, e.g. `0x`.
+    fn prefix(radix: u32) -> &'static str {
+        match radix {
+            2 => "0b",
+            8 => "0o",
+            16 => "0x",
+            _ => "",
+        }
+    }
+
+    /// Groups the integer digits of a formatted number in thousands, e.g.
+    /// `1,267,650`. Scientific notation is left alone.
+    fn grouped(text: String) -> String {
+        if text.contains('e') {
+            return text;
+        }
+        let (sign, unsigned) = match text.strip_prefix('-') {
+            Some(unsigned) => ("-", unsigned),
+            None => ("", text.as_str()),
+        };
+        let end = unsigned.find('.').unwrap_or(unsigned.len());
+        let (integer, rest) = unsigned.split_at(end);
+        if !integer.bytes().all(|b| b.is_ascii_digit()) {
+            return text;
+        }
+
+        let mut grouped = String::new();
+        for (i, digit) in integer.chars().enumerate() {
+            if i > 0 && (integer.len() - i).is_multiple_of(3) {
+                grouped.push(',');
+            }
+            grouped.push(digit);
+        }
+        format!("{sign}{grouped}{rest}")
+    }
+
+    /// Digits of `n` zero-padded to `width`, with the prefix of their base.
+    fn digits(mut n: u128, radix: u32, width: usize) -> String {
+        if !(2..=36).contains(&radix) {
+            return "-".to_string();
+        }
+
+        let mut digits = Vec::new();
+        loop {
+            let digit = std::char::from_digit((n % radix as u128) as u32, radix).unwrap();
+            digits.push(digit.to_ascii_uppercase());
+            n /= radix as u128;
+            if n == 0 && digits.len() >= width {
+                break;
+            }
+        }
+        let digits: String = digits.iter().rev().collect();
+
+        format!("{}{digits}", Self::prefix(radix))
+    }
+
+    fn ieee754(value: f64, bits: u32) -> String {
+        match bits {
+            _ if value.is_nan() => "-".to_string(),
+            32 => ieee754::describe_f32(value as f32),
+            _ => ieee754::describe_f64(value),
+        }
+    }
+
+    /// Amounts of money as `39.20 GBP`.
+    fn money(value: f64, currency: convert_chart::Currency) -> String {
+        if !value.is_finite() {
+            return "-".to_string();
+        }
+
+        format!("{:.2} {}", value, currency.code())
+    }
+
+    /// Degrees as `12°30'15"`, with seconds rounded to hundredths.
+    fn dms(value: f64) -> String {
+        if !value.is_finite() {
+            return "-".to_string();
+        }
+
+        let sign = if value < 0.0 { "-" } else { "" };
+        let hundredths = (value.abs() * 360_000.0).round() as u64;
+        let degrees = hundredths / 360_000;
+        let minutes = hundredths % 360_000 / 6_000;
+        let seconds = hundredths % 6_000;
+
+        let seconds = if seconds.is_multiple_of(100) {
+            (seconds / 100).to_string()
+        } else {
+            format!("{}.{:02}", seconds / 100, seconds % 100)
+                .trim_end_matches('0')
+                .to_string()
+        };
+
+        format!("{sign}{degrees}°{minutes}'{seconds}\"")
+    }
+}
+
 #[derive(Default, Clone)]
 struct CalculationResult {
     output: String,
@@ -44,11 +437,11 @@ impl CalculatorEngine {
         let mut env = parser::Env::default();
 
         for line in input.lines() {
-            let parsed = parser::parse_with_env(line, &mut env);
-            if parsed.is_normal() {
-                total += parsed;
+            let answer = parser::evaluate(line, &mut env);
+            if answer.value.is_normal() {
+                total += answer.value;
             }
-            output.push_str(&Formatter::number(parsed));
+            output.push_str(&Formatter::answer(&answer));
             output.push('\n');
         }
 
@@ -0,0 +1,194 @@
+//! Matrices, written as lists of rows such as `[[1, 2], [3, 4]]`.
+
+use crate::value::Value;
+
+pub type Matrix = Vec<Vec<Value>>;
+
+/// Largest matrix power, as exact entries grow quickly.
+const MAX_POWER: u64 = 1024;
+
+/// The rows of `value` when it is a matrix: a list of lists of numbers, all
+/// of the same non-zero length.
+pub fn rows(value: &Value) -> Option<Matrix> {
+    let Value::List(rows) = value else {
+        return None;
+    };
+    let rows: Matrix = rows
+        .iter()
+        .map(|row| match row {
+            Value::List(row) if row.iter().all(|x| !matches!(x, Value::List(_))) => {
+                Some(row.clone())
+            }
+            _ => None,
+        })
+        .collect::<Option<_>>()?;
+    let width = rows.first()?.len();
+    (width > 0 && rows.iter().all(|row| row.len() == width)).then_some(rows)
+}
+
+/// A list of numbers, taken as a column.
+fn column(value: &Value) -> Option<Matrix> {
+    let Value::List(items) = value else {
+        return None;
+    };
+    if items.is_empty() || items.iter().any(|x| matches!(x, Value::List(_))) {
+        return None;
+    }
+    Some(items.iter().map(|x| vec![x.clone()]).collect())
+}
+
+pub fn to_value(matrix: Matrix) -> Value {
+    Value::List(matrix.into_iter().map(Value::List).collect())
+}
+
+fn column_to_value(matrix: Matrix) -> Value {
+    Value::List(matrix.into_iter().flatten().collect())
+}
+
+fn identity(n: usize) -> Matrix {
+    (0..n)
+        .map(|i| (0..n).map(|j| Value::integer((i == j) as i64)).collect())
+        .collect()
+}
+
+fn multiply(a: &Matrix, b: &Matrix) -> Option<Matrix> {
+    if a[0].len() != b.len() {
+        return None;
+    }
+    let product = a
+        .iter()
+        .map(|row| {
+            (0..b[0].len())
+                .map(|j| {
+                    row.iter()
+                        .zip(b)
+                        .map(|(x, b_row)| x.clone() * b_row[j].clone())
+                        .fold(Value::integer(0), |sum, x| sum + x)
+                })
+                .collect()
+        })
+        .collect();
+    Some(product)
+}
+
+/// The matrix product of two matrices, or of a matrix and a vector. `None`
+/// when the operands aren't both matrices, so `*` stays element-wise.
+pub fn product(lhs: &Value, rhs: &Value) -> Option<Value> {
+    let a = rows(lhs)?;
+    let product = match (rows(rhs), column(rhs)) {
+        (Some(b), _) => multiply(&a, &b).map(to_value),
+        (None, Some(b)) => multiply(&a, &b).map(column_to_value),
+        (None, None) => return None,
+    };
+    Some(product.unwrap_or(Value::Float(f64::NAN)))
+}
+
+/// A square matrix to an integer power.
+pub fn power(matrix: &Value, exponent: &Value) -> Option<Value> {
+    let a = rows(matrix).filter(|a| a.len() == a[0].len())?;
+    let n = exponent.to_integer()?;
+    let (base, n) = match i64::try_from(n).ok()? {
+        n if n < 0 => (inverse(&a)?, n.unsigned_abs()),
+        n => (a, n as u64),
+    };
+    if n > MAX_POWER {
+        return Some(Value::Float(f64::NAN));
+    }
+    // Square and multiply.
+    let (mut result, mut base, mut n) = (identity(base.len()), base, n);
+    while n > 0 {
+        if n % 2 == 1 {
+            result = multiply(&result, &base)?;
+        }
+        base = multiply(&base, &base)?;
+        n /= 2;
+    }
+    Some(to_value(result))
+}
+
+pub fn transpose(a: &Matrix) -> Matrix {
+    (0..a[0].len())
+        .map(|j| a.iter().map(|row| row[j].clone()).collect())
+        .collect()
+}
+
+pub fn determinant(a: &Matrix) -> Option<Value> {
+    if a.len() != a[0].len() {
+        return None;
+    }
+    let mut a = a.clone();
+    let mut det = Value::integer(1);
+    for k in 0..a.len() {
+        let Some(pivot) = pivot(&a, k) else {
+            return Some(Value::integer(0));
+        };
+        if pivot != k {
+            a.swap(pivot, k);
+            det = Value::integer(0) - det;
+        }
+        det = det * a[k][k].clone();
+        let pivot_row = a[k].clone();
+        for row in a.iter_mut().skip(k + 1) {
+            let factor = row[k].clone() / pivot_row[k].clone();
+            subtract_row(row, &pivot_row, &factor);
+        }
+    }
+    Some(det)
+}
+
+pub fn inverse(a: &Matrix) -> Option<Matrix> {
+    solve(a, identity(a.len()))
+}
+
+/// Solves `a × x = b` by Gauss-Jordan elimination. `None` when `a` is
+/// singular or the sizes don't match.
+pub fn solve(a: &Matrix, b: Matrix) -> Option<Matrix> {
+    let n = a.len();
+    if a[0].len() != n || b.len() != n {
+        return None;
+    }
+    let mut rows: Matrix = a
+        .iter()
+        .zip(b)
+        .map(|(a, b)| [a.clone(), b].concat())
+        .collect();
+    for k in 0..n {
+        let pivot = pivot(&rows, k)?;
+        rows.swap(pivot, k);
+        let divisor = rows[k][k].clone();
+        for x in rows[k].iter_mut() {
+            *x = x.clone() / divisor.clone();
+        }
+        let pivot_row = rows[k].clone();
+        let others = rows.iter_mut().enumerate().filter(|&(i, _)| i != k);
+        for (_, row) in others {
+            let factor = row[k].clone();
+            subtract_row(row, &pivot_row, &factor);
+        }
+    }
+    Some(rows.into_iter().map(|row| row[n..].to_vec()).collect())
+}
+
+/// `solve` for values, where `b` may be a vector or a matrix.
+pub fn solve_values(a: &Value, b: &Value) -> Option<Value> {
+    let a = rows(a)?;
+    match (rows(b), column(b)) {
+        (Some(b), _) => solve(&a, b).map(to_value),
+        (None, Some(b)) => solve(&a, b).map(column_to_value),
+        (None, None) => None,
+    }
+}
+
+/// `row -= factor × pivot_row`.
+fn subtract_row(row: &mut [Value], pivot_row: &[Value], factor: &Value) {
+    for (x, p) in row.iter_mut().zip(pivot_row) {
+        *x = x.clone() - factor.clone() * p.clone();
+    }
+}
+
+/// The row at or below `k` with the largest non-zero entry in column `k`.
+fn pivot(a: &Matrix, k: usize) -> Option<usize> {
+    (k..a.len())
+        .filter(|&i| !a[i][k].is_zero())
+        .max_by(|&i, &j| a[i][k].magnitude().total_cmp(&a[j][k].magnitude()))
+}
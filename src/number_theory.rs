@@ -0,0 +1,228 @@
+//! Primes, factorisation and modular arithmetic on integers.
+
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{One, Signed, Zero};
+
+/// Small primes, for trial division and as Miller-Rabin witnesses.
+const SMALL_PRIMES: [u32; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+
+/// Largest divisor tried before switching to Pollard's rho.
+const TRIAL_LIMIT: u32 = 10_000;
+
+/// Pollard's rho steps before giving up on splitting a number.
+const MAX_RHO_STEPS: u32 = 1 << 16;
+
+/// Pollard's rho steps between gcds.
+const RHO_BATCH: u32 = 64;
+
+/// Largest input, in bits, that primality tests and factorisation accept, so
+/// that a sheet recomputed on every keystroke stays responsive.
+pub const MAX_BITS: u64 = 128;
+
+/// Whether `n` is prime, or `None` if it has more than `MAX_BITS` bits.
+pub fn is_prime(n: &BigInt) -> Option<bool> {
+    (n.bits() <= MAX_BITS).then(|| miller_rabin(n))
+}
+
+/// Miller-Rabin with the first twelve primes as witnesses, which is exact
+/// below 3 × 10^23 and very unlikely to be wrong above.
+fn miller_rabin(n: &BigInt) -> bool {
+    if n < &BigInt::from(2) {
+        return false;
+    }
+    for p in SMALL_PRIMES {
+        if n == &BigInt::from(p) {
+            return true;
+        }
+        if (n % p).is_zero() {
+            return false;
+        }
+    }
+    let n_minus_one: BigInt = n - 1;
+    let s = n_minus_one.trailing_zeros().unwrap_or(0);
+    let d = &n_minus_one >> s;
+    'witness: for a in SMALL_PRIMES {
+        let mut x = BigInt::from(a).modpow(&d, n);
+        if x.is_one() || x == n_minus_one {
+            continue;
+        }
+        for _ in 1..s {
+            x = &x * &x % n;
+            if x == n_minus_one {
+                continue 'witness;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+/// The smallest prime greater than `n`, or `None` if `n` has more than
+/// `MAX_BITS` bits.
+pub fn next_prime(n: &BigInt) -> Option<BigInt> {
+    if n.bits() > MAX_BITS {
+        return None;
+    }
+    let mut candidate = n.max(&BigInt::one()) + 1;
+    while !miller_rabin(&candidate) {
+        candidate += 1;
+    }
+    Some(candidate)
+}
+
+/// A factor of a factorisation, with its power.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Factor {
+    Prime(BigInt, u32),
+    /// A composite that Pollard's rho gave up on splitting.
+    Unfactored(BigInt),
+}
+
+/// The prime factors of `|n|` with their powers, smallest first, or `None`
+/// if `n` has more than `MAX_BITS` bits. Composites too hard to split in
+/// reasonable time are left whole, at the end.
+pub fn factor(n: &BigInt) -> Option<Vec<Factor>> {
+    if n.bits() > MAX_BITS {
+        return None;
+    }
+    let mut n = n.abs();
+    let mut factors = Vec::new();
+    let mut p = 2;
+    while p < TRIAL_LIMIT && BigInt::from(p * p) <= n {
+        let mut power = 0;
+        while (&n % p).is_zero() {
+            n /= p;
+            power += 1;
+        }
+        if power > 0 {
+            factors.push(Factor::Prime(BigInt::from(p), power));
+        }
+        p += if p == 2 { 1 } else { 2 };
+    }
+
+    let mut found = Vec::new();
+    split(n, &mut found);
+    found.sort();
+    for factor in found {
+        match (factors.last_mut(), factor) {
+            (Some(Factor::Prime(last, power)), Factor::Prime(prime, _)) if *last == prime => {
+                *power += 1
+            }
+            (_, factor) => factors.push(factor),
+        }
+    }
+    Some(factors)
+}
+
+/// Splits `n` into primes with Pollard's rho.
+fn split(n: BigInt, factors: &mut Vec<Factor>) {
+    if n <= BigInt::one() {
+        return;
+    }
+    if miller_rabin(&n) {
+        factors.push(Factor::Prime(n, 1));
+        return;
+    }
+    match rho(&n) {
+        Some(divisor) => {
+            let rest = &n / &divisor;
+            split(divisor, factors);
+            split(rest, factors);
+        }
+        None => factors.push(Factor::Unfactored(n)),
+    }
+}
+
+/// A non-trivial divisor of the composite `n`, if one turns up quickly. This
+/// is Brent's variant, which takes a gcd once per batch of steps. Another
+/// curve is only tried when one finds `n` itself, not when it runs out of
+/// steps.
+fn rho(n: &BigInt) -> Option<BigInt> {
+    'curve: for c in 1..=3 {
+        let step = |x: &BigInt| (x * x + c) % n;
+        let (mut x, mut y) = (BigInt::from(2), BigInt::from(2));
+        let mut product = BigInt::one();
+        let mut length = 1;
+        while length <= MAX_RHO_STEPS {
+            x.clone_from(&y);
+            for _ in 0..length {
+                y = step(&y);
+            }
+            for batch in (0..length).step_by(RHO_BATCH as usize) {
+                let saved = y.clone();
+                for _ in batch..length.min(batch + RHO_BATCH) {
+                    y = step(&y);
+                    product = product * (&x - &y).abs() % n;
+                }
+                let divisor = product.gcd(n);
+                if divisor.is_one() {
+                    continue;
+                }
+                // The batch overshot, so retrace it one step at a time.
+                let divisor = match divisor == *n {
+                    true => {
+                        let mut y = saved;
+                        loop {
+                            y = step(&y);
+                            let divisor = (&x - &y).abs().gcd(n);
+                            if !divisor.is_one() {
+                                break divisor;
+                            }
+                        }
+                    }
+                    false => divisor,
+                };
+                if divisor == *n {
+                    continue 'curve;
+                }
+                return Some(divisor);
+            }
+            length *= 2;
+        }
+        return None;
+    }
+    None
+}
+
+/// Euler's totient: how many integers up to `n` are coprime to it.
+pub fn totient(n: &BigInt) -> Option<BigInt> {
+    if !n.is_positive() {
+        return None;
+    }
+    factor(n)?
+        .iter()
+        .try_fold(BigInt::one(), |totient, factor| match factor {
+            Factor::Prime(p, power) => Some(totient * (p - 1) * p.pow(power - 1)),
+            Factor::Unfactored(_) => None,
+        })
+}
+
+pub fn gcd(values: &[BigInt]) -> Option<BigInt> {
+    let (first, rest) = values.split_first()?;
+    Some(rest.iter().fold(first.abs(), |a, b| a.gcd(b)))
+}
+
+pub fn lcm(values: &[BigInt]) -> Option<BigInt> {
+    let (first, rest) = values.split_first()?;
+    Some(rest.iter().fold(first.abs(), |a, b| a.lcm(b)))
+}
+
+/// `b^e mod m`, where a negative `e` uses the inverse of `b`.
+pub fn modpow(b: &BigInt, e: &BigInt, m: &BigInt) -> Option<BigInt> {
+    if !m.is_positive() {
+        return None;
+    }
+    match e.is_negative() {
+        true => Some(modinv(b, m)?.modpow(&-e, m)),
+        false => Some(b.modpow(e, m)),
+    }
+}
+
+/// The `x` with `a × x ≡ 1 (mod m)`, if `a` and `m` are coprime.
+pub fn modinv(a: &BigInt, m: &BigInt) -> Option<BigInt> {
+    if !m.is_positive() {
+        return None;
+    }
+    a.mod_floor(m).modinv(m)
+}
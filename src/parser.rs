@@ -5,8 +5,21 @@ use pest::iterators::{Pair, Pairs};
 use pest::pratt_parser::*;
 
 use std::collections::HashMap;
+use std::num::NonZeroU64;
 
-use crate::convert_chart::{UnitType, convert};
+use bigdecimal::{BigDecimal, RoundingMode, Signed, ToPrimitive, Zero};
+use num_bigint::BigInt;
+use num_complex::Complex64;
+use num_rational::BigRational;
+
+use crate::convert_chart::{
+    Amount, Angle, Context, Currency, Length, Mass, Time, Typography, UnitType, convert,
+    convert_with, dms_to_degrees, transfer_time,
+};
+use crate::dimension::{self, Dimension};
+use crate::programmer::{BinaryOp, Int, IntError, IntType, Overflow};
+use crate::value::{Comparison, MAX_EXACT_BITS, Value};
+use crate::{chemistry, combinatorics, constants, geo, matrix, number_theory, statistics};
 
 #[derive(Parser)]
 #[grammar = "grammar.pest"]
@@ -18,226 +31,1951 @@ lazy_static! {
         use Rule::*;
 
         PrattParser::new()
+            .op(Op::infix(or, Left))
+            .op(Op::infix(and, Left))
+            .op(Op::prefix(not))
+            .op(Op::infix(equal, Left)
+                | Op::infix(notEqual, Left)
+                | Op::infix(lessThan, Left)
+                | Op::infix(lessEqual, Left)
+                | Op::infix(greaterThan, Left)
+                | Op::infix(greaterEqual, Left))
+            .op(Op::infix(bitOr, Left))
+            .op(Op::infix(bitXor, Left))
+            .op(Op::infix(bitAnd, Left))
             .op(Op::infix(add, Left) | Op::infix(subtract, Left))
-            .op(Op::infix(multiply, Left) | Op::infix(divide, Left))
+            .op(Op::infix(multiply, Left)
+                | Op::infix(divide, Left)
+                | Op::infix(implicitMultiply, Left))
             .op(Op::infix(modulus, Left))
             .op(Op::infix(power, Right))
             .op(Op::infix(percentOf, Left) | Op::infix(percentOn, Left))
             .op(Op::infix(rightShift, Right) | Op::infix(leftShift, Right))
+            .op(Op::prefix(bitNot))
+            .op(Op::postfix(index) | Op::postfix(factorial))
     };
 }
 
 #[derive(Debug, Clone, Default)]
 pub struct Env {
-    vars: HashMap<String, f64>,
+    vars: HashMap<String, Value>,
     funcs: HashMap<String, FunctionDef>,
+    context: Context,
+    /// Exact values of variables assigned in programmer mode.
+    int_vars: HashMap<String, Int>,
+    /// Exact values of variables assigned in decimal mode.
+    decimal_vars: HashMap<String, BigDecimal>,
+    /// Dimensions of variables assigned a quantity, e.g. `d = 5 km`.
+    dimensions: HashMap<String, Dimension>,
+    mode: Mode,
+    overflow: Overflow,
+    /// Significant digits kept by decimal mode.
+    precision: Option<NonZeroU64>,
+    rounding: Rounding,
 }
 
-#[derive(Debug, Clone)]
+/// How a sheet evaluates its lines.
+#[derive(Debug, Clone, Copy, Default)]
+enum Mode {
+    #[default]
+    Float,
+    /// Programmer mode, with fixed-width integers.
+    Integer(IntType),
+    /// Arbitrary-precision decimals.
+    Decimal,
+}
+
+/// Decimal mode rounding.
+#[derive(Debug, Clone, Copy, Default)]
+enum Rounding {
+    #[default]
+    HalfEven,
+    HalfUp,
+}
+
+impl Env {
+    fn precision(&self) -> NonZeroU64 {
+        self.precision.unwrap_or(NonZeroU64::new(34).unwrap())
+    }
+
+    fn rounding_mode(&self) -> RoundingMode {
+        match self.rounding {
+            Rounding::HalfEven => RoundingMode::HalfEven,
+            Rounding::HalfUp => RoundingMode::HalfUp,
+        }
+    }
+
+    /// Rounds a decimal to the sheet's precision.
+    fn fit(&self, value: BigDecimal) -> BigDecimal {
+        if value.digits() > self.precision().get() {
+            value.with_precision_round(self.precision(), self.rounding_mode())
+        } else {
+            value
+        }
+    }
+}
+
+/// How the result of a line should be displayed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Notation {
+    #[default]
+    Decimal,
+    /// Degrees, minutes and seconds of arc.
+    Dms,
+    /// An integer written in the given base.
+    Radix(u32),
+    /// The IEEE-754 encoding of a float with the given number of bits.
+    Ieee754(u32),
+    /// An amount of money in the given currency.
+    Currency(Currency),
+    /// A fraction, e.g. `7/4`.
+    Fraction,
+    /// A whole number and a proper fraction, e.g. `1 3/4`.
+    MixedFraction,
+    /// Digits grouped in thousands, e.g. `1,048,576`.
+    Grouped,
+    /// A complex number as its magnitude and its angle in the given unit.
+    Polar(Angle),
+    /// A complex number as its real and imaginary parts.
+    Rectangular,
+    /// An integer as its prime factorisation, e.g. `2^3 × 3`.
+    Factors,
+    /// A quantity in SI base units, e.g. `299792458 m/s`.
+    Quantity(Dimension),
+}
+
+/// Result of evaluating one line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Answer {
+    pub value: f64,
+    pub notation: Notation,
+    /// The exact result, or one `value` can't hold.
+    pub exact: Option<Exact>,
+}
+
+/// Exact result of a line, or one that isn't a real number.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Exact {
+    /// Floating point mode, when only rationals were involved.
+    Rational(BigRational),
+    /// Floating point mode, when the result has an imaginary part.
+    Complex(Complex64),
+    /// Floating point mode, when the result is a list.
+    List(Vec<Value>),
+    /// Programmer mode.
+    Integer(Result<Int, IntError>),
+    /// Decimal mode, `None` when the line has no value.
+    Decimal(Option<BigDecimal>),
+    /// A line that can't be evaluated, with the reason.
+    Error(String),
+}
+
+impl Answer {
+    /// The answer for a value of the main evaluator.
+    pub fn new(value: Value, notation: Notation) -> Self {
+        Self {
+            value: value.to_f64(),
+            notation,
+            exact: match value {
+                Value::Float(_) => None,
+                Value::Rational(rational) => Some(Exact::Rational(rational)),
+                Value::Complex(complex) => Some(Exact::Complex(complex)),
+                Value::List(items) => Some(Exact::List(items)),
+            },
+        }
+    }
+}
+
+impl From<f64> for Answer {
+    fn from(value: f64) -> Self {
+        Self {
+            value,
+            notation: Notation::Decimal,
+            exact: None,
+        }
+    }
+}
+
+/// A user function, made of one or more pieces.
+#[derive(Debug, Clone, Default)]
 struct FunctionDef {
+    pieces: Vec<Piece>,
+}
+
+/// One piece of a user function, e.g. `0.1x when x < 10000`.
+#[derive(Debug, Clone)]
+struct Piece {
     param: String,
+    /// `None` for a plain definition or an `otherwise` piece.
+    condition: Option<String>,
     body: String,
 }
 
+impl FunctionDef {
+    /// The first piece whose condition holds, or else the `otherwise` piece.
+    /// `holds` evaluates a piece's condition, and `None` from it gives up.
+    fn piece(&self, mut holds: impl FnMut(&Piece, &str) -> Option<bool>) -> Option<&Piece> {
+        for piece in &self.pieces {
+            if let Some(condition) = &piece.condition
+                && holds(piece, condition)?
+            {
+                return Some(piece);
+            }
+        }
+        self.pieces.iter().rev().find(|p| p.condition.is_none())
+    }
+
+    /// Adds the pieces of a definition. A piecewise definition, one using
+    /// `when` or `otherwise`, extends the function and replaces its
+    /// `otherwise` piece if it has one; any other definition replaces it.
+    fn define(&mut self, pieces: Vec<Piece>, piecewise: bool) {
+        if !piecewise {
+            self.pieces = pieces;
+            return;
+        }
+        if pieces.iter().any(|p| p.condition.is_none()) {
+            self.pieces.retain(|p| p.condition.is_some());
+        }
+        self.pieces.extend(pieces);
+    }
+}
+
+/// Parses a condition or body stored as text.
+fn parse_expr(text: &str) -> Option<Pairs<'_, Rule>> {
+    Some(
+        Calculator::parse(Rule::expr, text)
+            .ok()?
+            .next()?
+            .into_inner(),
+    )
+}
+
+/// The comparison of a comparison operator.
+fn comparison(rule: Rule) -> Option<Comparison> {
+    match rule {
+        Rule::lessThan => Some(Comparison::Less),
+        Rule::lessEqual => Some(Comparison::LessEqual),
+        Rule::equal => Some(Comparison::Equal),
+        Rule::notEqual => Some(Comparison::NotEqual),
+        Rule::greaterEqual => Some(Comparison::GreaterEqual),
+        Rule::greaterThan => Some(Comparison::Greater),
+        _ => None,
+    }
+}
+
 fn eval_expr_str(
     expr: &str,
     env: &Env,
-    locals: Option<&HashMap<String, f64>>,
+    locals: Option<&HashMap<String, Value>>,
     depth: usize,
-) -> f64 {
+) -> Value {
     let parse_result = Calculator::parse(Rule::expr, expr);
     match parse_result {
         Ok(mut pairs) => {
             let Some(pair) = pairs.next() else {
-                return f64::NAN;
+                return Value::Float(f64::NAN);
             };
             eval(pair.into_inner(), env, locals, depth)
         }
-        Err(_) => f64::NAN,
+        Err(_) => Value::Float(f64::NAN),
     }
 }
 
 fn eval(
     expression: Pairs<Rule>,
     env: &Env,
-    locals: Option<&HashMap<String, f64>>,
+    locals: Option<&HashMap<String, Value>>,
     depth: usize,
-) -> f64 {
+) -> Value {
     // Basic recursion guard for user-defined functions.
     if depth > 64 {
-        return f64::NAN;
+        return Value::Float(f64::NAN);
+    }
+
+    PRATT_PARSER
+        .map_primary(|pair: Pair<Rule>| eval_primary(pair, env, locals, depth))
+        .map_infix(|lhs: Value, op: Pair<Rule>, rhs: Value| {
+            if let Some(comparison) = comparison(op.as_rule()) {
+                return lhs.compare(comparison, rhs);
+            }
+            match op.as_rule() {
+                Rule::add => lhs + rhs,
+                Rule::subtract => lhs - rhs,
+                Rule::multiply | Rule::implicitMultiply => lhs * rhs,
+                Rule::divide => lhs / rhs,
+                Rule::power => lhs.pow(rhs),
+                Rule::percentOf => percent_of(lhs, rhs),
+                Rule::percentOn => percent_on(lhs, rhs),
+                Rule::modulus => lhs % rhs,
+                Rule::rightShift => lhs.bitwise(BinaryOp::ShiftRight, rhs),
+                Rule::leftShift => lhs.bitwise(BinaryOp::ShiftLeft, rhs),
+                Rule::bitAnd => lhs.bitwise(BinaryOp::And, rhs),
+                Rule::bitOr => lhs.bitwise(BinaryOp::Or, rhs),
+                Rule::bitXor => lhs.bitwise(BinaryOp::Xor, rhs),
+                Rule::and => Value::from(lhs.is_true() && rhs.is_true()),
+                Rule::or => Value::from(lhs.is_true() || rhs.is_true()),
+                _ => Value::Float(f64::NAN),
+            }
+        })
+        .map_prefix(|op: Pair<Rule>, rhs: Value| match op.as_rule() {
+            Rule::bitNot => rhs.not(),
+            Rule::not => Value::from(!rhs.is_true()),
+            _ => Value::Float(f64::NAN),
+        })
+        .map_postfix(|lhs: Value, op: Pair<Rule>| match op.as_rule() {
+            Rule::index => lhs.index(&eval(op.into_inner(), env, locals, depth)),
+            Rule::factorial => combinatorics::factorial(lhs),
+            _ => Value::Float(f64::NAN),
+        })
+        .parse(expression)
+}
+
+/// Evaluates a single operand of an expression.
+fn eval_primary(
+    pair: Pair<Rule>,
+    env: &Env,
+    locals: Option<&HashMap<String, Value>>,
+    depth: usize,
+) -> Value {
+    match pair.as_rule() {
+        Rule::function => {
+            let mut i = pair.into_inner();
+            let name = i.next().unwrap().as_str();
+            // `if(condition, a, b)` only evaluates the branch it takes, so
+            // recursive functions can end.
+            if name == "if" && !env.funcs.contains_key(name) {
+                let (Some(condition), Some(then), Some(otherwise), None) =
+                    (i.next(), i.next(), i.next(), i.next())
+                else {
+                    return Value::Float(f64::NAN);
+                };
+                let branch = match eval(condition.into_inner(), env, locals, depth) {
+                    Value::Float(x) if x.is_nan() => return Value::Float(f64::NAN),
+                    condition if condition.is_true() => then,
+                    _ => otherwise,
+                };
+                return eval(branch.into_inner(), env, locals, depth);
+            }
+            // `map(f, list)` takes a function by name.
+            if name == "map" {
+                let (Some(function), Some(list), None) = (i.next(), i.next(), i.next()) else {
+                    return Value::Float(f64::NAN);
+                };
+                let function = function.as_str().trim();
+                return match eval(list.into_inner(), env, locals, depth) {
+                    Value::List(items) => Value::List(
+                        items
+                            .into_iter()
+                            .map(|item| call(function, vec![item], env, locals, depth))
+                            .collect(),
+                    ),
+                    _ => Value::Float(f64::NAN),
+                };
+            }
+
+            let args: Vec<Value> = i
+                .map(|arg| eval(arg.into_inner(), env, locals, depth))
+                .collect();
+            call(name, args, env, locals, depth)
+        }
+        Rule::list => Value::List(
+            pair.into_inner()
+                .map(|item| eval(item.into_inner(), env, locals, depth))
+                .collect(),
+        ),
+        Rule::imaginary => {
+            let factor = pair.into_inner().next().map_or(1.0, number);
+            Value::complex(Complex64::new(0.0, factor))
+        }
+        Rule::phasor => {
+            let mut i = pair.into_inner();
+            let magnitude = number(i.next().unwrap());
+            let angle = i.next().unwrap();
+            let degrees = match angle.as_rule() {
+                Rule::dms => dms(angle),
+                _ => match i.next().map(unit_type) {
+                    Some(Some(unit)) => {
+                        convert(number(angle), unit, UnitType::Angle(Angle::Degree))
+                    }
+                    Some(None) => f64::NAN,
+                    None => number(angle),
+                },
+            };
+            Value::complex(Complex64::from_polar(magnitude, degrees.to_radians()))
+        }
+        Rule::num | Rule::coefficient => {
+            let text = pair.as_str();
+            // Exact values have no negative zero, so `-0` stays a float.
+            if let value @ 0.0 = number(pair.clone())
+                && value.is_sign_negative()
+            {
+                return Value::Float(value);
+            }
+            Value::parse_integer(text)
+                .or_else(|| Value::parse_decimal(text))
+                .unwrap_or(Value::Float(number(pair)))
+        }
+        Rule::ident => {
+            let name = pair.as_str();
+            locals
+                .and_then(|l| l.get(name))
+                .or_else(|| env.vars.get(name))
+                .cloned()
+                .unwrap_or(Value::Float(f64::NAN))
+        }
+        Rule::expr => eval(pair.into_inner(), env, locals, depth),
+        _ => Value::Float(eval_quantity(pair, env)),
+    }
+}
+
+/// Calls a user function, or else a builtin.
+fn call(
+    name: &str,
+    args: Vec<Value>,
+    env: &Env,
+    locals: Option<&HashMap<String, Value>>,
+    depth: usize,
+) -> Value {
+    if let Some(def) = env.funcs.get(name) {
+        let Ok([value]) = <[Value; 1]>::try_from(args) else {
+            return Value::Float(f64::NAN);
+        };
+        let eval_piece = |piece: &Piece, text: &str| {
+            let mut next_locals = locals.cloned().unwrap_or_default();
+            next_locals.insert(piece.param.clone(), value.clone());
+            eval_expr_str(text, env, Some(&next_locals), depth + 1)
+        };
+        let piece = def.piece(|piece, condition| match eval_piece(piece, condition) {
+            Value::Float(x) if x.is_nan() => None,
+            condition => Some(condition.is_true()),
+        });
+        return match piece {
+            Some(piece) => eval_piece(piece, &piece.body),
+            None => Value::Float(f64::NAN),
+        };
+    }
+    if let Some(value) = apply_list_fun(name, &args) {
+        return value;
+    }
+    if let Some(value) = apply_matrix_fun(name, &args) {
+        return value;
+    }
+    if let Some(value) = apply_combinatorics_fun(name, &args) {
+        return value;
+    }
+    if let Some(value) = apply_number_theory_fun(name, &args) {
+        return value;
+    }
+    // Other builtins apply to each element of a list.
+    if let [Value::List(items)] = &args[..] {
+        return Value::List(
+            items
+                .iter()
+                .map(|item| call(name, vec![item.clone()], env, locals, depth))
+                .collect(),
+        );
+    }
+    if let Some(value) = apply_complex_fun(name, &args) {
+        return value;
+    }
+    let args: Vec<f64> = args.iter().map(Value::to_f64).collect();
+    Value::Float(apply_builtin_fun(name, &args))
+}
+
+/// Evaluates an operand that only exists in floating point, such as a unit
+/// conversion, an amount of money or a constant.
+fn eval_quantity(pair: Pair<Rule>, env: &Env) -> f64 {
+    match pair.as_rule() {
+        // Amounts are carried in US dollars until the line is displayed.
+        _ if let Some((amount, from)) = amount_of(&pair) => convert_with(
+            number(amount),
+            UnitType::Currency(from),
+            UnitType::Currency(Currency::Usd),
+            &env.context,
+        ),
+        Rule::convert => {
+            let mut i = pair.into_inner();
+            let quantity = i.next().unwrap();
+            if quantity.as_rule() == Rule::dms {
+                let to = unit_type(i.next().unwrap().into_inner().next().unwrap());
+                return match to {
+                    Some(to) => convert(dms(quantity), UnitType::Angle(Angle::Degree), to),
+                    None => f64::NAN,
+                };
+            }
+
+            let value = number(quantity);
+            let from = i.next().unwrap().into_inner().next().unwrap();
+            let to = i.next().unwrap().into_inner().next().unwrap();
+
+            match conversion_units(from, to) {
+                Some((from, to)) => convert_with(value, from, to, &env.context),
+                None => f64::NAN,
+            }
+        }
+        Rule::dms => dms(pair),
+        Rule::quantity => {
+            let mut i = pair.into_inner();
+            let value = number(i.next().unwrap());
+            match unit_type(i.next().unwrap()).and_then(|unit| Some((unit, dimension::of(unit)?))) {
+                Some((unit, (_, si))) => convert(value, unit, si),
+                None => f64::NAN,
+            }
+        }
+        Rule::molar_mass => {
+            let formula = pair.into_inner().next().unwrap().as_str();
+            chemistry::molar_mass(formula).unwrap_or(f64::NAN)
+        }
+        Rule::stoichiometry => {
+            let mut i = pair.into_inner();
+            let value = number(i.next().unwrap());
+            let from = unit_type(i.next().unwrap());
+            let molar_mass = chemistry::molar_mass(i.next().unwrap().as_str());
+            let to = unit_type(i.next().unwrap());
+
+            let (Some(from), Some(molar_mass), Some(to)) = (from, molar_mass, to) else {
+                return f64::NAN;
+            };
+            let grams = UnitType::Mass(Mass::Gram);
+            let moles = match from {
+                UnitType::Mass(_) => convert(value, from, grams) / molar_mass,
+                _ => convert(value, from, UnitType::Amount(Amount::Mole)),
+            };
+            match to {
+                UnitType::Mass(_) => convert(moles * molar_mass, grams, to),
+                _ => convert(moles, UnitType::Amount(Amount::Mole), to),
+            }
+        }
+        Rule::transfer => {
+            let mut i = pair.into_inner();
+            let size = number(i.next().unwrap());
+            let size_unit = unit_type(i.next().unwrap());
+            let rate = number(i.next().unwrap());
+            let rate_unit = unit_type(i.next().unwrap());
+            let to = match i.next() {
+                Some(time) => unit_type(time),
+                None => Some(UnitType::Time(Time::Second)),
+            };
+
+            match (size_unit, rate_unit, to) {
+                (Some(size_unit), Some(rate_unit), Some(to)) => convert(
+                    transfer_time(size, size_unit, rate, rate_unit),
+                    UnitType::Time(Time::Second),
+                    to,
+                ),
+                _ => f64::NAN,
+            }
+        }
+        Rule::pi => std::f64::consts::PI,
+        Rule::e => std::f64::consts::E,
+        Rule::tau => std::f64::consts::TAU,
+        Rule::physical => constants::physical(&pair.as_str()["phys.".len()..])
+            .map_or(f64::NAN, |(value, _)| value),
+        _ => f64::NAN,
+    }
+}
+
+/// A float as the shortest decimal that reads back as it.
+fn decimal_of(value: f64) -> Option<BigDecimal> {
+    value.is_finite().then(|| value.to_string().parse().ok())?
+}
+
+/// A decimal as a value, exact when it is an integer.
+fn value_of_decimal(value: &BigDecimal) -> Value {
+    match value.is_integer() {
+        true => Value::from(value.with_scale(0).into_bigint_and_exponent().0),
+        false => Value::Float(value.to_f64().unwrap_or(f64::NAN)),
+    }
+}
+
+/// A value as a decimal, exact when it is an integer.
+fn decimal_of_value(value: Value) -> Option<BigDecimal> {
+    match value.to_integer() {
+        Some(integer) => Some(BigDecimal::new(integer, 0)),
+        None => decimal_of(value.to_f64()),
+    }
+}
+
+/// Evaluates an expression in decimal mode. Money is converted straight into
+/// the currency of the line, so amounts already in it stay exact.
+fn eval_decimal(
+    expression: Pairs<Rule>,
+    env: &Env,
+    currency: Option<Currency>,
+    locals: Option<&HashMap<String, BigDecimal>>,
+    depth: usize,
+) -> Option<BigDecimal> {
+    // Basic recursion guard for user-defined functions.
+    if depth > 64 {
+        return None;
     }
 
     PRATT_PARSER
         .map_primary(|pair: Pair<Rule>| match pair.as_rule() {
-            Rule::convert => {
-                let mut i = pair.into_inner();
-                let value = i.next().unwrap().as_str().parse::<f64>().unwrap();
-                // Try to figure out rule name for the conversion between units
-                // weight = kilo to gram
-                // length = kilometer to meter
-                let si_unit_type = i
-                    .clone()
-                    .next()
-                    .unwrap()
-                    .into_inner()
-                    .next()
-                    .unwrap()
-                    .as_rule();
-                let from = i
-                    .next()
-                    .unwrap()
-                    .into_inner()
-                    .next()
-                    .unwrap()
-                    .into_inner()
-                    .next()
-                    .unwrap()
-                    .as_rule();
-                let to = i
-                    .next()
-                    .unwrap()
-                    .into_inner()
-                    .next()
-                    .unwrap()
-                    .into_inner()
-                    .next()
-                    .unwrap()
-                    .as_rule();
-
-                if let (Ok(from), Ok(to)) = (
-                    format!("{:?}::{:?}", si_unit_type, from).parse::<UnitType>(),
-                    format!("{:?}::{:?}", si_unit_type, to).parse::<UnitType>(),
-                ) {
-                    convert(value, from, to)
-                } else {
-                    f64::NAN
+            Rule::num | Rule::coefficient => match pair.as_str().get(..2) {
+                Some("0x" | "0b" | "0o") => decimal_of(number(pair)),
+                _ => pair.as_str().trim().replace('_', "").parse().ok(),
+            },
+            Rule::ident => {
+                let name = pair.as_str();
+                match locals
+                    .and_then(|l| l.get(name))
+                    .or(env.decimal_vars.get(name))
+                {
+                    Some(value) => Some(value.clone()),
+                    None => decimal_of(env.vars.get(name)?.to_f64()),
+                }
+            }
+            _ if let Some((amount, from)) = amount_of(&pair) => {
+                let amount: BigDecimal = amount.as_str().trim().replace('_', "").parse().ok()?;
+                let to = currency?;
+                if from == to {
+                    return Some(amount);
                 }
+                let rate = |c: Currency| {
+                    let rate = env.context.rates.get(&c).copied()?;
+                    (rate > 0.0).then_some(rate).and_then(decimal_of)
+                };
+                Some(env.fit(amount * rate(from)? / rate(to)?))
             }
             Rule::function => {
                 let mut i = pair.into_inner();
                 let name = i.next().unwrap().as_str();
-                let value = eval(i, env, locals, depth);
+                let args = i
+                    .map(|arg| eval_decimal(arg.into_inner(), env, currency, locals, depth))
+                    .collect::<Option<Vec<BigDecimal>>>()?;
 
                 if let Some(def) = env.funcs.get(name) {
-                    let mut next_locals: HashMap<String, f64> = locals.cloned().unwrap_or_default();
-                    next_locals.insert(def.param.clone(), value);
-                    eval_expr_str(&def.body, env, Some(&next_locals), depth + 1)
-                } else {
-                    apply_builtin_fun(name, value)
+                    let [value] = <[BigDecimal; 1]>::try_from(args).ok()?;
+                    let eval_piece = |piece: &Piece, text: &str| {
+                        let mut next_locals = locals.cloned().unwrap_or_default();
+                        next_locals.insert(piece.param.clone(), value.clone());
+                        let body = parse_expr(text)?;
+                        eval_decimal(body, env, currency, Some(&next_locals), depth + 1)
+                    };
+                    let piece = def
+                        .piece(|piece, condition| Some(!eval_piece(piece, condition)?.is_zero()))?;
+                    return eval_piece(piece, &piece.body);
                 }
+
+                let mode = env.rounding_mode();
+                let values: Vec<Value> = args.iter().map(value_of_decimal).collect();
+                let value = match (name, &args[..]) {
+                    ("round", [x]) => Some(x.with_scale_round(0, mode)),
+                    ("round", [x, places]) => Some(x.with_scale_round(places.to_i64()?, mode)),
+                    ("floor", [x]) => Some(x.with_scale_round(0, RoundingMode::Floor)),
+                    ("ceil", [x]) => Some(x.with_scale_round(0, RoundingMode::Ceiling)),
+                    ("abs", [x]) => Some(x.abs()),
+                    ("trunc", [x]) => Some(x.with_scale_round(0, RoundingMode::Down)),
+                    ("frac", [x]) => Some(x - x.with_scale_round(0, RoundingMode::Down)),
+                    ("sign", [x]) => Some(x.signum()),
+                    _ if let Some(value) = apply_combinatorics_fun(name, &values)
+                        .or_else(|| apply_number_theory_fun(name, &values)) =>
+                    {
+                        decimal_of_value(value)
+                    }
+                    // Everything else is evaluated in floating point.
+                    _ => {
+                        let args: Vec<f64> =
+                            args.iter().map(|a| a.to_f64()).collect::<Option<_>>()?;
+                        decimal_of(apply_builtin_fun(name, &args))
+                    }
+                };
+                value.map(|value| env.fit(value))
+            }
+            Rule::expr => eval_decimal(pair.into_inner(), env, currency, locals, depth),
+            // Units, constants and the like are evaluated in floating point.
+            _ => {
+                let locals: Option<HashMap<String, Value>> = locals.map(|l| {
+                    l.iter()
+                        .map(|(k, v)| (k.clone(), Value::Float(v.to_f64().unwrap_or(f64::NAN))))
+                        .collect()
+                });
+                let value = eval_primary(pair, env, locals.as_ref(), depth);
+                decimal_of(value.to_f64()).map(|value| env.fit(value))
+            }
+        })
+        .map_infix(|lhs, op: Pair<Rule>, rhs| {
+            let (lhs, rhs) = (lhs?, rhs?);
+            let boolean = |b: bool| Some(BigDecimal::from(b as u8));
+            if let Some(comparison) = comparison(op.as_rule()) {
+                return boolean(comparison.holds(Some(lhs.cmp(&rhs))));
+            }
+            Some(
+                env.fit(match op.as_rule() {
+                    Rule::add => lhs + rhs,
+                    Rule::subtract => lhs - rhs,
+                    Rule::multiply | Rule::implicitMultiply => lhs * rhs,
+                    Rule::and => return boolean(!lhs.is_zero() && !rhs.is_zero()),
+                    Rule::or => return boolean(!lhs.is_zero() || !rhs.is_zero()),
+                    Rule::divide if rhs.is_zero() => return None,
+                    Rule::divide => {
+                        (lhs / rhs).with_precision_round(env.precision(), env.rounding_mode())
+                    }
+                    Rule::modulus if rhs.is_zero() => return None,
+                    Rule::modulus => lhs % rhs,
+                    Rule::power if rhs.is_integer() => match rhs.to_i64()? {
+                        n if n < 0 && lhs.is_zero() => return None,
+                        // Too large to keep exactly, so evaluated in floating point.
+                        n if lhs
+                            .as_bigint_and_scale()
+                            .0
+                            .bits()
+                            .saturating_mul(n.unsigned_abs())
+                            > MAX_EXACT_BITS =>
+                        {
+                            decimal_of(lhs.to_f64()?.powf(n as f64))?
+                        }
+                        n if n < 0 => {
+                            let inverse = BigDecimal::from(1) / lhs.powi(-n);
+                            inverse.with_precision_round(env.precision(), env.rounding_mode())
+                        }
+                        n => lhs.powi(n),
+                    },
+                    Rule::percentOf => lhs * rhs / BigDecimal::from(100),
+                    Rule::percentOn => &lhs * &rhs / BigDecimal::from(100) + rhs,
+                    // Shifts and bitwise operators work on exact integers.
+                    Rule::rightShift
+                    | Rule::leftShift
+                    | Rule::bitAnd
+                    | Rule::bitOr
+                    | Rule::bitXor => {
+                        let op = match op.as_rule() {
+                            Rule::rightShift => BinaryOp::ShiftRight,
+                            Rule::leftShift => BinaryOp::ShiftLeft,
+                            Rule::bitAnd => BinaryOp::And,
+                            Rule::bitOr => BinaryOp::Or,
+                            _ => BinaryOp::Xor,
+                        };
+                        let value = value_of_decimal(&lhs).bitwise(op, value_of_decimal(&rhs));
+                        decimal_of_value(value)?
+                    }
+                    // Everything else is evaluated in floating point.
+                    _ => {
+                        let (lhs, rhs) = (lhs.to_f64()?, rhs.to_f64()?);
+                        let value = match op.as_rule() {
+                            Rule::power => lhs.powf(rhs),
+                            _ => f64::NAN,
+                        };
+                        decimal_of(value)?
+                    }
+                }),
+            )
+        })
+        .map_prefix(|op: Pair<Rule>, rhs| match op.as_rule() {
+            Rule::bitNot => decimal_of(!(rhs?.to_i64()?) as f64),
+            Rule::not => Some(BigDecimal::from(rhs?.is_zero() as u8)),
+            _ => None,
+        })
+        // Lists are only evaluated in floating point.
+        .map_postfix(|lhs, op: Pair<Rule>| match op.as_rule() {
+            Rule::factorial => {
+                let value = combinatorics::factorial(value_of_decimal(&lhs?));
+                decimal_of_value(value).map(|value| env.fit(value))
             }
-            Rule::pi => std::f64::consts::PI,
-            Rule::e => std::f64::consts::E,
-            Rule::tau => std::f64::consts::TAU,
-            Rule::num => pair.as_str().trim().parse::<f64>().unwrap(),
+            _ => None,
+        })
+        .parse(expression)
+}
+
+/// Evaluates an expression in programmer mode, with the fixed-width integer
+/// semantics of `ty`.
+fn eval_int(
+    expression: Pairs<Rule>,
+    env: &Env,
+    ty: IntType,
+    locals: Option<&HashMap<String, Int>>,
+    depth: usize,
+) -> Result<Int, IntError> {
+    // Basic recursion guard for user-defined functions.
+    if depth > 64 {
+        return Err(IntError::Unsupported);
+    }
+    let overflow = env.overflow;
+
+    PRATT_PARSER
+        .map_primary(|pair: Pair<Rule>| match pair.as_rule() {
+            Rule::num | Rule::coefficient => Int::parse(pair.as_str(), ty, overflow),
             Rule::ident => {
                 let name = pair.as_str();
-                locals
-                    .and_then(|l| l.get(name).copied())
-                    .or_else(|| env.vars.get(name).copied())
-                    .unwrap_or(f64::NAN)
-            }
-            Rule::expr => eval(pair.into_inner(), env, locals, depth),
-            _ => f64::NAN,
-        })
-        .map_infix(|lhs: f64, op: Pair<Rule>, rhs: f64| match op.as_rule() {
-            Rule::add => lhs + rhs,
-            Rule::subtract => lhs - rhs,
-            Rule::multiply => lhs * rhs,
-            Rule::divide => lhs / rhs,
-            Rule::power => lhs.powf(rhs),
-            Rule::percentOf => percent_of(lhs, rhs),
-            Rule::percentOn => percent_on(lhs, rhs),
-            Rule::rightShift => (lhs as i64 >> rhs as i64) as f64,
-            Rule::leftShift => ((lhs as i64) << rhs as i64) as f64,
-            Rule::modulus => lhs % rhs,
-            _ => f64::NAN,
+                match locals.and_then(|l| l.get(name)).or(env.int_vars.get(name)) {
+                    Some(value) => value.cast(ty, overflow),
+                    None => match env.vars.get(name) {
+                        Some(value) => Int::from_f64(value.to_f64(), ty, overflow),
+                        None => Err(IntError::Unsupported),
+                    },
+                }
+            }
+            Rule::function => {
+                let mut i = pair.into_inner();
+                let name = i.next().unwrap().as_str();
+                let args = i
+                    .map(|arg| eval_int(arg.into_inner(), env, ty, locals, depth))
+                    .collect::<Result<Vec<Int>, IntError>>()?;
+
+                if let Some(def) = env.funcs.get(name) {
+                    let [value] = args[..] else {
+                        return Err(IntError::Unsupported);
+                    };
+                    let eval_piece = |piece: &Piece, text: &str| {
+                        let mut next_locals = locals.cloned().unwrap_or_default();
+                        next_locals.insert(piece.param.clone(), value);
+                        let body = parse_expr(text).ok_or(IntError::Unsupported)?;
+                        eval_int(body, env, ty, Some(&next_locals), depth + 1)
+                    };
+                    let mut error = IntError::Unsupported;
+                    let piece = def.piece(|piece, condition| match eval_piece(piece, condition) {
+                        Ok(condition) => Some(!condition.is_zero()),
+                        Err(e) => {
+                            error = e;
+                            None
+                        }
+                    });
+                    match piece {
+                        Some(piece) => eval_piece(piece, &piece.body),
+                        None => Err(error),
+                    }
+                } else {
+                    match (name, &args[..]) {
+                        ("rotl", &[value, amount]) => Ok(value.rotate_left(amount)),
+                        ("rotr", &[value, amount]) => Ok(value.rotate_right(amount)),
+                        ("nCr" | "choose", &[n, k]) => {
+                            let value = combinatorics::exact_choose(&n.to_bigint(), &k.to_bigint());
+                            int_of_exact(value, &[n, k], ty, overflow)
+                        }
+                        ("nPr" | "perm", &[n, k]) => {
+                            let value =
+                                combinatorics::exact_permutations(&n.to_bigint(), &k.to_bigint());
+                            int_of_exact(value, &[n, k], ty, overflow)
+                        }
+                        _ => {
+                            let values: Vec<Value> = args
+                                .iter()
+                                .map(|arg| Value::from(arg.to_bigint()))
+                                .collect();
+                            let value = apply_number_theory_fun(name, &values)
+                                .ok_or(IntError::Unsupported)?;
+                            let value = value.to_integer().ok_or(IntError::Unsupported)?;
+                            Int::from_bigint(&value, ty, overflow)
+                        }
+                    }
+                }
+            }
+            Rule::pi | Rule::e | Rule::tau | Rule::physical => Err(IntError::NotAnInteger),
+            Rule::expr => eval_int(pair.into_inner(), env, ty, locals, depth),
+            _ => Err(IntError::Unsupported),
+        })
+        .map_infix(|lhs, op: Pair<Rule>, rhs| {
+            let boolean = |b: bool| Int::new(false, b as u128, ty, overflow);
+            if let Some(comparison) = comparison(op.as_rule()) {
+                return boolean(comparison.holds(Some(lhs?.compare(rhs?))));
+            }
+            let op = match op.as_rule() {
+                Rule::and => return boolean(!lhs?.is_zero() && !rhs?.is_zero()),
+                Rule::or => return boolean(!lhs?.is_zero() || !rhs?.is_zero()),
+                Rule::add => BinaryOp::Add,
+                Rule::subtract => BinaryOp::Subtract,
+                Rule::multiply | Rule::implicitMultiply => BinaryOp::Multiply,
+                Rule::divide => BinaryOp::Divide,
+                Rule::modulus => BinaryOp::Remainder,
+                Rule::power => BinaryOp::Power,
+                Rule::leftShift => BinaryOp::ShiftLeft,
+                Rule::rightShift => BinaryOp::ShiftRight,
+                Rule::bitAnd => BinaryOp::And,
+                Rule::bitOr => BinaryOp::Or,
+                Rule::bitXor => BinaryOp::Xor,
+                _ => return Err(IntError::Unsupported),
+            };
+            lhs?.binary(op, rhs?, overflow)
+        })
+        .map_prefix(|op: Pair<Rule>, rhs| match op.as_rule() {
+            Rule::bitNot => rhs.map(Int::not),
+            Rule::not => Int::new(false, rhs?.is_zero() as u128, ty, overflow),
+            _ => Err(IntError::Unsupported),
+        })
+        .map_postfix(|lhs, op: Pair<Rule>| match op.as_rule() {
+            Rule::factorial => {
+                let n = lhs?;
+                let value = combinatorics::exact_factorial(&n.to_bigint());
+                int_of_exact(value, &[n], ty, overflow)
+            }
+            _ => Err(IntError::Unsupported),
         })
         .parse(expression)
 }
 
-fn percent_on(a: f64, b: f64) -> f64 {
-    a / 100_f64 * b + b
+/// A factorial or the like in programmer mode, where `value` is `None` when
+/// an argument is negative or the result is too large to work out.
+fn int_of_exact(
+    value: Option<BigInt>,
+    args: &[Int],
+    ty: IntType,
+    overflow: Overflow,
+) -> Result<Int, IntError> {
+    match value {
+        Some(value) => Int::from_bigint(&value, ty, overflow),
+        None if args.iter().any(|arg| arg.to_f64() < 0.0) => Err(IntError::Unsupported),
+        None => Err(IntError::Overflow),
+    }
+}
+
+/// Value of a `num` pair, which may be `0x`, `0b` or `0o` prefixed and may
+/// group digits with underscores.
+fn number(pair: Pair<Rule>) -> f64 {
+    let text = pair.as_str().trim().replace('_', "");
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text.strip_prefix('+').unwrap_or(&text)),
+    };
+    let radix = match digits.get(..2) {
+        Some("0x") => 16,
+        Some("0b") => 2,
+        Some("0o") => 8,
+        _ => return text.parse::<f64>().unwrap_or(f64::NAN),
+    };
+    let value = u128::from_str_radix(&digits[2..], radix).map_or(f64::NAN, |v| v as f64);
+    if negative { -value } else { value }
+}
+
+/// An angle in degrees, or NaN if its minutes or seconds are 60 or more or
+/// a part before the last has a fraction.
+fn dms(pair: Pair<Rule>) -> f64 {
+    let negative = pair.as_str().starts_with('-');
+    let (mut degrees, mut minutes, mut seconds) = (0_f64, 0_f64, 0_f64);
+    let mut fraction = false;
+    for part in pair.into_inner() {
+        if fraction {
+            return f64::NAN;
+        }
+        let text = part.as_str();
+        fraction = text.contains('.');
+        let number = text
+            .trim_end_matches(|c: char| !c.is_ascii_digit())
+            .parse::<f64>()
+            .unwrap_or(f64::NAN);
+        match part.as_rule() {
+            Rule::dms_degrees => degrees = number,
+            Rule::dms_minutes => minutes = number,
+            Rule::dms_seconds => seconds = number,
+            _ => {}
+        }
+    }
+    if minutes >= 60.0 || seconds >= 60.0 {
+        return f64::NAN;
+    }
+    dms_to_degrees(negative, degrees, minutes, seconds)
+}
+
+/// Resolves both sides of a conversion into units of the same category.
+///
+/// Some units (e.g. `in`) belong to more than one category, and the grammar
+/// picks the first one that matches. Try the category of each side in turn,
+/// so that `1 in to px` is read as typography.
+fn conversion_units(from: Pair<Rule>, to: Pair<Rule>) -> Option<(UnitType, UnitType)> {
+    let from_unit = from.clone().into_inner().next()?.as_rule();
+    let to_unit = to.clone().into_inner().next()?.as_rule();
+    [from.as_rule(), to.as_rule()]
+        .into_iter()
+        .find_map(|category| {
+            let from = format!("{:?}::{:?}", category, from_unit).parse().ok()?;
+            let to = format!("{:?}::{:?}", category, to_unit).parse().ok()?;
+            Some((from, to))
+        })
+}
+
+/// The amount and currency of money, or of money being converted, e.g.
+/// `100 usd to eur`.
+fn amount_of<'a>(pair: &Pair<'a, Rule>) -> Option<(Pair<'a, Rule>, Currency)> {
+    let mut inner = pair.clone().into_inner();
+    match pair.as_rule() {
+        Rule::money => {
+            let amount = inner.clone().find(|p| p.as_rule() == Rule::num)?;
+            let currency = inner.find(|p| p.as_rule() == Rule::CURRENCY)?;
+            Some((amount, currency_of(currency)?))
+        }
+        Rule::convert => {
+            let amount = inner.next().filter(|p| p.as_rule() == Rule::num)?;
+            let from = currency_of(inner.next()?.into_inner().next()?)?;
+            currency_of(inner.next()?.into_inner().next()?)?;
+            Some((amount, from))
+        }
+        _ => None,
+    }
+}
+
+/// Resolves a `CURRENCY` pair into its currency.
+fn currency_of(pair: Pair<Rule>) -> Option<Currency> {
+    match unit_type(pair)? {
+        UnitType::Currency(currency) => Some(currency),
+        _ => None,
+    }
+}
+
+/// Converts an amount carried in US dollars into the currency of the line.
+fn in_currency(value: f64, currency: Option<Currency>, context: &Context) -> f64 {
+    match currency {
+        Some(currency) => convert_with(
+            value,
+            UnitType::Currency(Currency::Usd),
+            UnitType::Currency(currency),
+            context,
+        ),
+        None => value,
+    }
+}
+
+/// `in_currency` for values of the main evaluator, converting lists element
+/// by element.
+fn value_in_currency(value: Value, currency: Option<Currency>, context: &Context) -> Value {
+    match (value, currency) {
+        (value, None) => value,
+        (Value::List(items), _) => Value::List(
+            items
+                .into_iter()
+                .map(|item| value_in_currency(item, currency, context))
+                .collect(),
+        ),
+        (value, _) => Value::Float(in_currency(value.to_f64(), currency, context)),
+    }
 }
 
-fn percent_of(a: f64, b: f64) -> f64 {
-    a / 100_f64 * b
+/// Resolves a unit category pair (e.g. `LENGTH`) into the unit it holds.
+fn unit_type(category: Pair<Rule>) -> Option<UnitType> {
+    let category_rule = category.as_rule();
+    let unit = category.into_inner().next()?.as_rule();
+    format!("{:?}::{:?}", category_rule, unit).parse().ok()
 }
 
-fn apply_builtin_fun(name: &str, arg: f64) -> f64 {
+fn percent_on(a: Value, b: Value) -> Value {
+    percent_of(a, b.clone()) + b
+}
+
+fn percent_of(a: Value, b: Value) -> Value {
+    a / Value::integer(100) * b
+}
+
+/// Functions of a whole list. Reductions also take their values as separate
+/// arguments, as in `max(1, 5, 3)`.
+fn apply_list_fun(name: &str, args: &[Value]) -> Option<Value> {
+    let items = match args {
+        [Value::List(items)] => items,
+        _ => args,
+    };
     match name {
-        "sin" => arg.to_radians().sin(),
-        "cos" => arg.to_radians().cos(),
-        "tan" => arg.to_radians().tan(),
-        "asin" => arg.asin(),
-        "acos" => arg.acos(),
-        "atan" => arg.atan(),
-        "sinh" => arg.sinh(),
-        "cosh" => arg.cosh(),
-        "tanh" => arg.tanh(),
-        "asinh" => arg.asinh(),
-        "acosh" => arg.acosh(),
-        "atanh" => arg.atanh(),
-        "log" => arg.log10(),
-        "sqrt" => arg.sqrt(),
-        "cbrt" => arg.cbrt(),
-        "round" => arg.round(),
-        "ceil" => arg.ceil(),
-        "floor" => arg.floor(),
+        "len" => match args {
+            [Value::List(items)] => Some(Value::integer(items.len() as i64)),
+            _ => None,
+        },
+        "sum" => Some(statistics::sum(items)),
+        "mean" => Some(statistics::mean(items)),
+        "median" => Some(statistics::median(items)),
+        "stdev" => Some(statistics::stdev(items)),
+        "min" => Some(statistics::min(items)),
+        "max" => Some(statistics::max(items)),
+        _ => None,
+    }
+}
+
+fn apply_matrix_fun(name: &str, args: &[Value]) -> Option<Value> {
+    let nan = Value::Float(f64::NAN);
+    match (name, args) {
+        ("transpose", [a]) => {
+            Some(matrix::rows(a).map_or(nan, |a| matrix::to_value(matrix::transpose(&a))))
+        }
+        ("det", [a]) => Some(
+            matrix::rows(a)
+                .and_then(|a| matrix::determinant(&a))
+                .unwrap_or(nan),
+        ),
+        ("inv" | "inverse", [a]) => Some(
+            matrix::rows(a)
+                .and_then(|a| matrix::inverse(&a))
+                .map_or(nan, matrix::to_value),
+        ),
+        ("solve", [a, b]) => Some(matrix::solve_values(a, b).unwrap_or(nan)),
+        _ => None,
+    }
+}
+
+fn apply_combinatorics_fun(name: &str, args: &[Value]) -> Option<Value> {
+    match (name, args) {
+        ("nCr" | "choose", [n, k]) => Some(combinatorics::choose(n.clone(), k.clone())),
+        ("nPr" | "perm", [n, k]) => Some(combinatorics::permutations(n.clone(), k.clone())),
+        _ => None,
+    }
+}
+
+/// Number theory functions, which take integers. `factor` gives back its
+/// argument, and a line that is just a `factor` call is shown factorised.
+fn apply_number_theory_fun(name: &str, args: &[Value]) -> Option<Value> {
+    const NAMES: [&str; 8] = [
+        "isprime",
+        "nextprime",
+        "factor",
+        "totient",
+        "gcd",
+        "lcm",
+        "modpow",
+        "modinv",
+    ];
+    if !NAMES.contains(&name) {
+        return None;
+    }
+    let items = match args {
+        [Value::List(items)] if matches!(name, "gcd" | "lcm") => items,
+        // The others apply to each element of a list.
+        [Value::List(_)] => return None,
+        _ => args,
+    };
+    let Some(n) = items
+        .iter()
+        .map(Value::to_whole)
+        .collect::<Option<Vec<_>>>()
+    else {
+        return Some(Value::Float(f64::NAN));
+    };
+    let value = match (name, &n[..]) {
+        ("isprime", [n]) => number_theory::is_prime(n).map(Value::from),
+        ("nextprime", [n]) => number_theory::next_prime(n).map(Value::from),
+        ("factor", [n]) if n.bits() <= number_theory::MAX_BITS => Some(Value::from(n.clone())),
+        ("totient", [n]) => number_theory::totient(n).map(Value::from),
+        ("gcd", n) => number_theory::gcd(n).map(Value::from),
+        ("lcm", n) => number_theory::lcm(n).map(Value::from),
+        ("modpow", [b, e, m]) => number_theory::modpow(b, e, m).map(Value::from),
+        ("modinv", [a, m]) => number_theory::modinv(a, m).map(Value::from),
+        _ => None,
+    };
+    Some(value.unwrap_or(Value::Float(f64::NAN)))
+}
+
+/// Functions that take or return complex numbers. `None` leaves the call to
+/// the real-valued builtins.
+fn apply_complex_fun(name: &str, args: &[Value]) -> Option<Value> {
+    match (name, args) {
+        ("sqrt", [Value::Complex(z)]) => Some(Value::complex(z.sqrt())),
+        ("sqrt", [x]) if x.to_f64() < 0.0 => {
+            Some(Value::complex(Complex64::new(0.0, (-x.to_f64()).sqrt())))
+        }
+        ("abs", [Value::Complex(z)]) => Some(Value::Float(z.norm())),
+        ("ln", [Value::Complex(z)]) => Some(Value::complex(z.ln())),
+        ("ln", [x]) if x.to_f64() < 0.0 => Some(Value::complex(x.to_complex().ln())),
+        ("exp", [Value::Complex(z)]) => Some(Value::complex(z.exp())),
+        ("arg", [z]) => Some(Value::Float(z.to_complex().arg().to_degrees())),
+        ("conj", [Value::Complex(z)]) => Some(Value::complex(z.conj())),
+        ("re", [Value::Complex(z)]) => Some(Value::Float(z.re)),
+        ("im", [Value::Complex(z)]) => Some(Value::Float(z.im)),
+        ("conj" | "re", [x]) => Some(x.clone()),
+        ("im", [_]) => Some(Value::integer(0)),
+        ("polar", [r, theta]) => Some(Value::complex(Complex64::from_polar(
+            r.to_f64(),
+            theta.to_f64().to_radians(),
+        ))),
+        _ => None,
+    }
+}
+
+/// How a built-in function is evaluated in floating point.
+#[derive(Clone, Copy)]
+enum Builtin {
+    /// A function of one number.
+    Unary(fn(f64) -> f64),
+    /// A function of two points, each given as latitude and longitude, with
+    /// the unit of its result.
+    Geo(fn(f64, f64, f64, f64) -> f64, UnitType),
+    /// Handled before the floating point builtins: conditionals, lists,
+    /// matrices, combinatorics, number theory, complex numbers and bit
+    /// rotations.
+    Other,
+}
+
+/// The built-in functions, in every mode.
+#[rustfmt::skip]
+const FUNCTIONS: &[(&str, Builtin)] = &[
+    ("sin", Builtin::Unary(|x| x.to_radians().sin())),
+    ("cos", Builtin::Unary(|x| x.to_radians().cos())),
+    ("tan", Builtin::Unary(|x| x.to_radians().tan())),
+    ("sec", Builtin::Unary(|x| 1.0 / x.to_radians().cos())),
+    ("csc", Builtin::Unary(|x| 1.0 / x.to_radians().sin())),
+    ("cot", Builtin::Unary(|x| 1.0 / x.to_radians().tan())),
+    ("asin", Builtin::Unary(f64::asin)),
+    ("acos", Builtin::Unary(f64::acos)),
+    ("atan", Builtin::Unary(f64::atan)),
+    ("deg", Builtin::Unary(f64::to_degrees)),
+    ("rad", Builtin::Unary(f64::to_radians)),
+    ("sinh", Builtin::Unary(f64::sinh)),
+    ("cosh", Builtin::Unary(f64::cosh)),
+    ("tanh", Builtin::Unary(f64::tanh)),
+    ("asinh", Builtin::Unary(f64::asinh)),
+    ("acosh", Builtin::Unary(f64::acosh)),
+    ("atanh", Builtin::Unary(f64::atanh)),
+    ("log", Builtin::Unary(f64::log10)),
+    ("ln", Builtin::Unary(f64::ln)),
+    ("log2", Builtin::Unary(f64::log2)),
+    ("exp", Builtin::Unary(f64::exp)),
+    ("sqrt", Builtin::Unary(f64::sqrt)),
+    ("cbrt", Builtin::Unary(f64::cbrt)),
+    ("abs", Builtin::Unary(f64::abs)),
+    ("sign", Builtin::Unary(|x| if x == 0.0 { 0.0 } else { x.signum() })),
+    ("round", Builtin::Unary(f64::round)),
+    ("ceil", Builtin::Unary(f64::ceil)),
+    ("floor", Builtin::Unary(f64::floor)),
+    ("trunc", Builtin::Unary(f64::trunc)),
+    ("frac", Builtin::Unary(f64::fract)),
+    ("distance", Builtin::Geo(|lat1, lon1, lat2, lon2| convert(
+        geo::distance(lat1, lon1, lat2, lon2),
+        UnitType::Length(Length::Metre),
+        UnitType::Length(Length::Kilometre),
+    ), UnitType::Length(Length::Kilometre))),
+    ("bearing", Builtin::Geo(|lat1, lon1, lat2, lon2| convert(
+        geo::bearing(lat1, lon1, lat2, lon2),
+        UnitType::Angle(Angle::Radian),
+        UnitType::Angle(Angle::Degree),
+    ), UnitType::Angle(Angle::Degree))),
+    ("if", Builtin::Other), ("map", Builtin::Other),
+    ("len", Builtin::Other), ("sum", Builtin::Other), ("mean", Builtin::Other),
+    ("median", Builtin::Other), ("stdev", Builtin::Other),
+    ("min", Builtin::Other), ("max", Builtin::Other),
+    ("transpose", Builtin::Other), ("det", Builtin::Other), ("inv", Builtin::Other),
+    ("inverse", Builtin::Other), ("solve", Builtin::Other),
+    ("arg", Builtin::Other), ("conj", Builtin::Other), ("re", Builtin::Other),
+    ("im", Builtin::Other), ("polar", Builtin::Other),
+    ("nCr", Builtin::Other), ("choose", Builtin::Other),
+    ("nPr", Builtin::Other), ("perm", Builtin::Other),
+    ("isprime", Builtin::Other), ("nextprime", Builtin::Other), ("factor", Builtin::Other),
+    ("totient", Builtin::Other), ("gcd", Builtin::Other), ("lcm", Builtin::Other),
+    ("modpow", Builtin::Other), ("modinv", Builtin::Other),
+    ("rotl", Builtin::Other), ("rotr", Builtin::Other),
+];
+
+/// The built-in function called `name`, if there is one.
+fn builtin(name: &str) -> Option<Builtin> {
+    FUNCTIONS
+        .iter()
+        .find(|&&(known, _)| known == name)
+        .map(|&(_, function)| function)
+}
+
+fn apply_builtin_fun(name: &str, args: &[f64]) -> f64 {
+    match (builtin(name), args) {
+        (Some(Builtin::Unary(function)), &[arg]) => function(arg),
+        (Some(Builtin::Geo(function, _)), &[lat1, lon1, lat2, lon2]) => {
+            function(lat1, lon1, lat2, lon2)
+        }
         _ => f64::NAN,
     }
 }
 
+#[cfg(test)]
 pub fn parse_with_env(input: &str, env: &mut Env) -> f64 {
+    evaluate(input, env).value
+}
+
+/// Evaluates one line of the sheet, updating `env` with any definitions.
+pub fn evaluate(input: &str, env: &mut Env) -> Answer {
     let parse_result = Calculator::parse(Rule::calculation, input);
     match parse_result {
         Ok(mut pairs) => {
             let Some(pair) = pairs.next() else {
-                return f64::NAN;
+                return Answer::from(f64::NAN);
             };
-
-            match pair.as_rule() {
-                // Feed the Pratt parser the actual expression token stream.
-                Rule::calculation | Rule::stmt => eval(pair.into_inner(), env, None, 0),
-                Rule::expr => eval(pair.into_inner(), env, None, 0),
-                Rule::fun_def => {
-                    let mut inner = pair.into_inner();
-                    let Some(name) = inner.next().map(|p| p.as_str().to_string()) else {
-                        return f64::NAN;
-                    };
-                    let Some(param) = inner.next().map(|p| p.as_str().to_string()) else {
-                        return f64::NAN;
-                    };
-                    let Some(expr_pair) = inner.next() else {
-                        return f64::NAN;
+            // A unit asked for with `to`, which the result is converted into.
+            let unit = pairs
+                .peek()
+                .filter(|p| p.as_rule() == Rule::unit_notation)
+                .and_then(|p| p.into_inner().next())
+                .and_then(|siunit| {
+                    let name = siunit.as_str();
+                    Some((unit_type(siunit.into_inner().next()?)?, name))
+                });
+            let notation = match pairs.next() {
+                Some(p) if p.as_rule() == Rule::dms_notation => Notation::Dms,
+                Some(p) if p.as_rule() == Rule::fraction_notation => Notation::Fraction,
+                Some(p) if p.as_rule() == Rule::mixed_notation => Notation::MixedFraction,
+                Some(p) if p.as_rule() == Rule::grouped_notation => Notation::Grouped,
+                Some(p) if p.as_rule() == Rule::polar_notation => {
+                    match p.into_inner().next().and_then(unit_type) {
+                        Some(UnitType::Angle(unit)) => Notation::Polar(unit),
+                        _ => Notation::Polar(Angle::Degree),
+                    }
+                }
+                Some(p) if p.as_rule() == Rule::rectangular_notation => Notation::Rectangular,
+                Some(p) if p.as_rule() == Rule::factors_notation => Notation::Factors,
+                Some(p) if p.as_rule() == Rule::ieee754_notation => {
+                    match p.into_inner().next().map(|p| p.as_rule()) {
+                        Some(Rule::single) => Notation::Ieee754(32),
+                        _ => Notation::Ieee754(64),
+                    }
+                }
+                Some(p) if p.as_rule() == Rule::hex_notation => Notation::Radix(16),
+                Some(p) if p.as_rule() == Rule::bin_notation => Notation::Radix(2),
+                Some(p) if p.as_rule() == Rule::oct_notation => Notation::Radix(8),
+                Some(p) if p.as_rule() == Rule::base_notation => {
+                    let base = p.as_str()["base".len()..].trim().parse().unwrap_or(0);
+                    Notation::Radix(base)
+                }
+                Some(p) if p.as_rule() == Rule::CURRENCY => {
+                    currency_of(p).map_or(Notation::Decimal, Notation::Currency)
+                }
+                _ => Notation::Decimal,
+            };
+            // Money is shown in the requested currency, or else in the first
+            // one written on the line.
+            let notation = match notation {
+                Notation::Decimal if is_factor_call(&pair, env) => Notation::Factors,
+                Notation::Decimal => pair
+                    .clone()
+                    .into_inner()
+                    .flatten()
+                    .find_map(|p| match amount_of(&p) {
+                        // A conversion is shown in the currency it converts to.
+                        Some(_) if p.as_rule() == Rule::convert => {
+                            currency_of(p.into_inner().last()?.into_inner().next()?)
+                        }
+                        amount => amount.map(|(_, currency)| currency),
+                    })
+                    .map_or(Notation::Decimal, Notation::Currency),
+                notation => notation,
+            };
+            // A ratio of two amounts is a plain number, and a product of two
+            // is not money at all.
+            let expression = match pair.as_rule() {
+                Rule::assign => pair
+                    .clone()
+                    .into_inner()
+                    .find(|p| p.as_rule() == Rule::expr),
+                Rule::expr => Some(pair.clone()),
+                _ => None,
+            };
+            let notation = match expression
+                .clone()
+                .map(|expr| money_power(expr.into_inner()))
+            {
+                Some(0) if has_money(&pair) => Notation::Decimal,
+                Some(power) if power != 1 && has_money(&pair) => {
+                    return Answer {
+                        value: f64::NAN,
+                        notation: Notation::Decimal,
+                        exact: Some(Exact::Error(
+                            "amounts of money can't be multiplied together".to_string(),
+                        )),
                     };
-                    env.funcs.insert(
-                        name,
-                        FunctionDef {
-                            param,
-                            body: expr_pair.as_str().to_string(),
-                        },
-                    );
-                    // Function definitions don't produce a numeric result.
-                    f64::NAN
-                }
-                Rule::assign => {
-                    let mut inner = pair.into_inner();
-                    let Some(ident) = inner.next().map(|p| p.as_str().to_string()) else {
-                        return f64::NAN;
+                }
+                _ => notation,
+            };
+            let currency = match notation {
+                Notation::Currency(currency) => Some(currency),
+                _ => None,
+            };
+
+            if pair.as_rule() == Rule::rate {
+                return match set_rate(pair, env) {
+                    Ok(()) => Answer::from(f64::NAN),
+                    Err(message) => Answer {
+                        value: f64::NAN,
+                        notation,
+                        exact: Some(Exact::Error(message)),
+                    },
+                };
+            }
+
+            if let Some(message) = constant_error(&pair) {
+                return Answer {
+                    value: f64::NAN,
+                    notation,
+                    exact: Some(Exact::Error(message)),
+                };
+            }
+
+            if let Some(name) = unknown_function(&pair, env) {
+                return Answer {
+                    value: f64::NAN,
+                    notation,
+                    exact: Some(Exact::Error(unknown_function_error(name, env))),
+                };
+            }
+
+            let dimension = match expression.map(|expr| dimension_of(expr.into_inner(), env)) {
+                Some(Ok(dimension)) => dimension,
+                Some(Err(message)) => {
+                    return Answer {
+                        value: f64::NAN,
+                        notation,
+                        exact: Some(Exact::Error(message)),
                     };
-                    let Some(expr_pair) = inner.next() else {
-                        return f64::NAN;
+                }
+                None => Dimension::NONE,
+            };
+
+            let conversion = match unit {
+                Some((to, name)) if let Some((function, from)) = quantity_unit(&pair, env) => {
+                    if std::mem::discriminant(&from) != std::mem::discriminant(&to) {
+                        return Answer {
+                            value: f64::NAN,
+                            notation,
+                            exact: Some(Exact::Error(format!(
+                                "{function} can't be converted to {name}"
+                            ))),
+                        };
+                    }
+                    Some((from, to))
+                }
+                Some((to, name)) if !dimension.is_none() => match dimension::of(to) {
+                    Some((of, si)) if of == dimension => Some((si, to)),
+                    _ => {
+                        return Answer {
+                            value: f64::NAN,
+                            notation,
+                            exact: Some(Exact::Error(format!(
+                                "{dimension} can't be converted to {name}"
+                            ))),
+                        };
+                    }
+                },
+                Some(_) => {
+                    return Answer {
+                        value: f64::NAN,
+                        notation,
+                        exact: Some(Exact::Error(
+                            "only results with a unit can be converted".to_string(),
+                        )),
                     };
-                    let value = eval(expr_pair.into_inner(), env, None, 0);
-                    env.vars.insert(ident, value);
-                    value
                 }
-                _ => eval(pair.into_inner(), env, None, 0),
+                None => None,
+            };
+            // Anything else with a unit is shown in SI base units.
+            let notation = match notation {
+                Notation::Decimal if conversion.is_none() && !dimension.is_none() => {
+                    Notation::Quantity(dimension)
+                }
+                notation => notation,
+            };
+            if pair.as_rule() == Rule::assign
+                && let Some(name) = pair.clone().into_inner().next()
+            {
+                env.dimensions.insert(name.as_str().to_string(), dimension);
             }
+            let converted = |value: f64| match conversion {
+                Some((from, to)) => convert(value, from, to),
+                None => value,
+            };
+
+            if matches!(pair.as_rule(), Rule::expr | Rule::assign) {
+                match env.mode {
+                    Mode::Float => {
+                        let value = match eval_float_stmt(pair, env, currency) {
+                            value if conversion.is_some() => {
+                                Value::Float(converted(value.to_f64()))
+                            }
+                            value => value,
+                        };
+                        return Answer::new(value, notation);
+                    }
+                    Mode::Integer(_) if conversion.is_some() => {
+                        return Answer {
+                            value: f64::NAN,
+                            notation,
+                            exact: Some(Exact::Integer(Err(IntError::Unsupported))),
+                        };
+                    }
+                    Mode::Integer(ty) => {
+                        let integer = eval_int_stmt(pair, env, ty);
+                        return Answer {
+                            value: integer.map_or(f64::NAN, Int::to_f64),
+                            notation,
+                            exact: Some(Exact::Integer(integer)),
+                        };
+                    }
+                    Mode::Decimal => {
+                        let mut decimal = eval_decimal_stmt(pair, env, currency);
+                        if conversion.is_some() {
+                            decimal = decimal
+                                .and_then(|d| decimal_of(converted(d.to_f64()?)))
+                                .map(|d| env.fit(d));
+                        }
+                        // Money is shown to the cent.
+                        if currency.is_some() {
+                            decimal = decimal.map(|d| d.with_scale_round(2, env.rounding_mode()));
+                        }
+                        return Answer {
+                            value: decimal
+                                .as_ref()
+                                .and_then(|d| d.to_f64())
+                                .unwrap_or(f64::NAN),
+                            notation,
+                            exact: Some(Exact::Decimal(decimal)),
+                        };
+                    }
+                }
+            }
+
+            Answer {
+                value: eval_stmt(pair, env),
+                notation,
+                exact: None,
+            }
+        }
+        Err(_) => Answer::from(f64::NAN),
+    }
+}
+
+/// The first function the line calls, or passes to `map`, that is neither
+/// built in nor defined.
+fn unknown_function<'a>(pair: &Pair<'a, Rule>, env: &Env) -> Option<&'a str> {
+    // A definition may call the function it defines.
+    let defining = match pair.as_rule() {
+        Rule::fun_def => pair.clone().into_inner().next().map(|ident| ident.as_str()),
+        _ => None,
+    };
+    pair.clone()
+        .into_inner()
+        .flatten()
+        .filter(|p| p.as_rule() == Rule::function)
+        .flat_map(|function| {
+            let mut inner = function.into_inner();
+            let name = inner.next().map(|ident| ident.as_str());
+            let mapped = match name {
+                Some("map") => inner.next().map(|function| function.as_str().trim()),
+                _ => None,
+            };
+            name.into_iter().chain(mapped)
+        })
+        .find(|&name| {
+            Some(name) != defining && builtin(name).is_none() && !env.funcs.contains_key(name)
+        })
+}
+
+/// Says a function is unknown, suggesting known ones with similar names.
+fn unknown_function_error(name: &str, env: &Env) -> String {
+    let known = FUNCTIONS
+        .iter()
+        .map(|&(known, _)| known)
+        .chain(env.funcs.keys().map(String::as_str));
+    let mut similar: Vec<(usize, &str)> = known
+        .map(|known| (edit_distance(name, known), known))
+        .filter(|&(distance, _)| distance <= 1 || distance == 2 && name.len() > 3)
+        .collect();
+    similar.sort();
+    let similar: Vec<&str> = similar.iter().take(5).map(|&(_, known)| known).collect();
+    match similar.as_slice() {
+        [] => format!("unknown function {name}"),
+        similar => format!(
+            "unknown function {name}, did you mean {}?",
+            similar.join(", ")
+        ),
+    }
+}
+
+/// Optimal string alignment distance, counting characters: like Levenshtein,
+/// but swapping two neighbours is one edit, so `sni` is one away from `sin`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = (a[i - 1] != b[j - 1]) as usize;
+            d[i][j] = (d[i - 1][j - 1] + cost)
+                .min(d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+/// Whether the line writes an amount of money.
+fn has_money(pair: &Pair<Rule>) -> bool {
+    pair.clone()
+        .into_inner()
+        .flatten()
+        .any(|p| p.as_rule() == Rule::money)
+}
+
+/// How many amounts of money are multiplied together in an expression: 1 for
+/// money, 0 for a plain number such as the ratio of two amounts.
+fn money_power(expression: Pairs<Rule>) -> i32 {
+    PRATT_PARSER
+        .map_primary(|pair: Pair<Rule>| match pair.as_rule() {
+            Rule::money => 1,
+            Rule::expr => money_power(pair.into_inner()),
+            Rule::function | Rule::list => pair
+                .into_inner()
+                .filter(|p| p.as_rule() == Rule::expr)
+                .map(|arg| money_power(arg.into_inner()))
+                .max()
+                .unwrap_or(0),
+            _ => 0,
+        })
+        .map_infix(|lhs: i32, op: Pair<Rule>, rhs: i32| match op.as_rule() {
+            Rule::multiply | Rule::implicitMultiply => lhs + rhs,
+            Rule::divide => lhs - rhs,
+            Rule::power | Rule::modulus => lhs,
+            _ => lhs.max(rhs),
+        })
+        .map_prefix(|_, rhs: i32| rhs)
+        .map_postfix(|lhs: i32, _| lhs)
+        .parse(expression)
+}
+
+/// The dimension of an expression, from the units of its quantities and
+/// physical constants. Sums, comparisons and most functions need their
+/// operands to have the same dimension.
+fn dimension_of(expression: Pairs<Rule>, env: &Env) -> Result<Dimension, String> {
+    operand_dimension(expression, env).map(|(dimension, _)| dimension)
+}
+
+/// The dimension of an expression, and its value if it is a small whole
+/// number, which a quantity can be raised to.
+fn operand_dimension(
+    expression: Pairs<Rule>,
+    env: &Env,
+) -> Result<(Dimension, Option<i8>), String> {
+    PRATT_PARSER
+        .map_primary(|pair: Pair<Rule>| match pair.as_rule() {
+            Rule::num => Ok((Dimension::NONE, pair.as_str().trim().parse().ok())),
+            Rule::quantity => {
+                let unit = pair.into_inner().nth(1).and_then(unit_type);
+                let dimension = unit.and_then(dimension::of).map(|(dimension, _)| dimension);
+                Ok((dimension.unwrap_or_default(), None))
+            }
+            Rule::physical => {
+                let constant = constants::physical(&pair.as_str()["phys.".len()..]);
+                Ok((
+                    constant.map(|(_, dimension)| dimension).unwrap_or_default(),
+                    None,
+                ))
+            }
+            Rule::ident => Ok((
+                env.dimensions
+                    .get(pair.as_str())
+                    .copied()
+                    .unwrap_or_default(),
+                None,
+            )),
+            Rule::expr => operand_dimension(pair.into_inner(), env),
+            Rule::list => {
+                let items = pair
+                    .into_inner()
+                    .map(|item| dimension_of(item.into_inner(), env))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok((same_dimension(&items, "put")?, None))
+            }
+            Rule::function => Ok((call_dimension(pair, env)?, None)),
+            _ => Ok((Dimension::NONE, None)),
+        })
+        .map_infix(|lhs, op: Pair<Rule>, rhs| {
+            let ((lhs, _), (rhs, exponent)) = (lhs?, rhs?);
+            let dimension = match op.as_rule() {
+                Rule::multiply | Rule::implicitMultiply => lhs * rhs,
+                Rule::divide => lhs / rhs,
+                _ if lhs.is_none() && rhs.is_none() => Dimension::NONE,
+                Rule::power if !rhs.is_none() => {
+                    return Err(format!("can't raise to a power in {rhs}"));
+                }
+                Rule::power => exponent
+                    .and_then(|n| lhs.powi(n))
+                    .ok_or_else(|| format!("{lhs} can only be raised to a whole number"))?,
+                Rule::add => same_dimension(&[lhs, rhs], "add")?,
+                Rule::subtract => same_dimension(&[lhs, rhs], "subtract")?,
+                Rule::modulus => same_dimension(&[lhs, rhs], "divide")?,
+                rule if comparison(rule).is_some() => {
+                    same_dimension(&[lhs, rhs], "compare")?;
+                    Dimension::NONE
+                }
+                _ => {
+                    let op = op.as_str().trim();
+                    return Err(format!("{op} needs plain numbers"));
+                }
+            };
+            Ok((dimension, None))
+        })
+        .map_prefix(|op: Pair<Rule>, rhs| match rhs? {
+            (rhs, _) if rhs.is_none() => Ok((rhs, None)),
+            _ => Err(format!("{} needs a plain number", op.as_str().trim())),
+        })
+        .map_postfix(|lhs, op: Pair<Rule>| match (lhs?, op.as_rule()) {
+            ((lhs, _), Rule::index) => Ok((lhs, None)),
+            ((lhs, _), _) if lhs.is_none() => Ok((lhs, None)),
+            _ => Err("a factorial needs a plain number".to_string()),
+        })
+        .parse(expression)
+}
+
+/// The dimension shared by all of `dimensions`, or else says they can't be
+/// combined, e.g. "can't add m and s".
+fn same_dimension(dimensions: &[Dimension], verb: &str) -> Result<Dimension, String> {
+    let first = dimensions.first().copied().unwrap_or_default();
+    match dimensions.iter().find(|&&dimension| dimension != first) {
+        Some(other) => Err(format!(
+            "can't {verb} {} and {}",
+            describe(first),
+            describe(*other)
+        )),
+        None => Ok(first),
+    }
+}
+
+fn describe(dimension: Dimension) -> String {
+    match dimension.is_none() {
+        true => "a plain number".to_string(),
+        false => dimension.to_string(),
+    }
+}
+
+/// The dimension of a function call. Functions that round, pick or average
+/// keep the dimension of their arguments and `sqrt` halves it; every other
+/// function takes plain numbers.
+fn call_dimension(function: Pair<Rule>, env: &Env) -> Result<Dimension, String> {
+    let mut inner = function.into_inner();
+    let name = inner.next().map_or("", |ident| ident.as_str());
+    let args = inner
+        .filter(|arg| arg.as_rule() == Rule::expr)
+        .map(|arg| dimension_of(arg.into_inner(), env))
+        .collect::<Result<Vec<_>, _>>()?;
+    if args.iter().all(|arg| arg.is_none()) {
+        return Ok(Dimension::NONE);
+    }
+    let builtin = !env.funcs.contains_key(name);
+    match (name, args.as_slice()) {
+        ("sqrt", &[arg]) if builtin => arg
+            .sqrt()
+            .ok_or_else(|| format!("can't take the square root of {arg}")),
+        ("round", &[arg, places]) if builtin && places.is_none() => Ok(arg),
+        ("abs" | "round" | "ceil" | "floor" | "trunc", &[arg]) if builtin => Ok(arg),
+        ("min" | "max" | "sum" | "mean" | "median" | "stdev", args) if builtin => {
+            same_dimension(args, "combine")
+        }
+        _ => Err(format!("{name} needs plain numbers")),
+    }
+}
+
+/// The name and unit of a line that is just a call to a built-in with one,
+/// such as `distance`, which gives kilometres.
+fn quantity_unit<'a>(pair: &Pair<'a, Rule>, env: &Env) -> Option<(&'a str, UnitType)> {
+    let mut inner = pair.clone().into_inner();
+    match (pair.as_rule(), inner.next(), inner.next()) {
+        (Rule::expr, Some(function), None) if function.as_rule() == Rule::function => {
+            let name = function.into_inner().next()?.as_str();
+            match builtin(name) {
+                Some(Builtin::Geo(_, unit)) if !env.funcs.contains_key(name) => Some((name, unit)),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Whether the line is just a call to the built-in `factor`.
+fn is_factor_call(pair: &Pair<Rule>, env: &Env) -> bool {
+    let mut inner = pair.clone().into_inner();
+    match (pair.as_rule(), inner.next(), inner.next()) {
+        (Rule::expr, Some(function), None) if function.as_rule() == Rule::function => {
+            let name = function.into_inner().next().map(|ident| ident.as_str());
+            name == Some("factor") && !env.funcs.contains_key("factor")
+        }
+        _ => false,
+    }
+}
+
+/// Whether `name` is a constant, which can't be reassigned.
+fn is_constant(name: &str) -> bool {
+    Calculator::parse(Rule::constants, name).is_ok_and(|p| p.as_str() == name)
+}
+
+/// Says a line can't assign to a constant such as `pi` or `i`, or take one
+/// as a parameter.
+fn constant_error(pair: &Pair<Rule>) -> Option<String> {
+    let mut inner = pair.clone().into_inner();
+    let (name, role) = match pair.as_rule() {
+        Rule::assign => (inner.next()?.as_str(), "assigned"),
+        Rule::fun_def => (inner.nth(1)?.as_str(), "a parameter"),
+        _ => return None,
+    };
+    is_constant(name).then(|| format!("{name} is a constant and can't be {role}"))
+}
+
+fn eval_decimal_stmt(
+    pair: Pair<Rule>,
+    env: &mut Env,
+    currency: Option<Currency>,
+) -> Option<BigDecimal> {
+    if pair.as_rule() == Rule::expr {
+        return eval_decimal(pair.into_inner(), env, currency, None, 0);
+    }
+
+    let mut inner = pair.into_inner();
+    let ident = inner.next()?.as_str().to_string();
+    let value = eval_decimal(inner.next()?.into_inner(), env, currency, None, 0)?;
+    env.vars.insert(
+        ident.clone(),
+        Value::Float(value.to_f64().unwrap_or(f64::NAN)),
+    );
+    env.int_vars.remove(&ident);
+    env.decimal_vars.insert(ident, value.clone());
+    Some(value)
+}
+
+fn eval_int_stmt(pair: Pair<Rule>, env: &mut Env, ty: IntType) -> Result<Int, IntError> {
+    if pair.as_rule() == Rule::expr {
+        return eval_int(pair.into_inner(), env, ty, None, 0);
+    }
+
+    let mut inner = pair.into_inner();
+    let ident = inner.next().unwrap().as_str().to_string();
+    let value = eval_int(inner.next().unwrap().into_inner(), env, ty, None, 0)?;
+    env.vars.insert(ident.clone(), Value::Float(value.to_f64()));
+    env.decimal_vars.remove(&ident);
+    env.int_vars.insert(ident, value);
+    Ok(value)
+}
+
+fn eval_float_stmt(pair: Pair<Rule>, env: &mut Env, currency: Option<Currency>) -> Value {
+    let in_currency = |value: Value, env: &Env| value_in_currency(value, currency, &env.context);
+    if pair.as_rule() == Rule::expr {
+        return in_currency(eval(pair.into_inner(), env, None, 0), env);
+    }
+
+    let mut inner = pair.into_inner();
+    let ident = inner.next().unwrap().as_str().to_string();
+    let value = in_currency(eval(inner.next().unwrap().into_inner(), env, None, 0), env);
+    env.int_vars.remove(&ident);
+    env.decimal_vars.remove(&ident);
+    env.vars.insert(ident, value.clone());
+    value
+}
+
+/// Applies a `rate` directive, e.g. `rate EUR/USD = 1.08`.
+fn set_rate(pair: Pair<Rule>, env: &mut Env) -> Result<(), String> {
+    let mut inner = pair.into_inner();
+    let (Some(base), Some(quote)) = (
+        inner.next().and_then(currency_of),
+        inner.next().and_then(currency_of),
+    ) else {
+        return Err("unknown currency".to_string());
+    };
+    if base == quote {
+        return Err(format!("{} can't have a rate against itself", base.code()));
+    }
+    let Some(rate) = inner.next().map(number).filter(|rate| *rate > 0.0) else {
+        return Err("a rate must be positive".to_string());
+    };
+    // `rate EUR/USD = 1.08` means one euro buys 1.08 dollars.
+    let Some(quote_in_usd) = env.context.rates.get(&quote).copied() else {
+        return Err(format!("no rate for {}", quote.code()));
+    };
+    env.context.rates.insert(base, rate * quote_in_usd);
+    Ok(())
+}
+
+fn eval_stmt(pair: Pair<Rule>, env: &mut Env) -> f64 {
+    match pair.as_rule() {
+        // Feed the Pratt parser the actual expression token stream.
+        Rule::calculation | Rule::stmt => eval(pair.into_inner(), env, None, 0).to_f64(),
+        Rule::fun_def => {
+            let mut inner = pair.into_inner();
+            let Some(name) = inner.next().map(|p| p.as_str().to_string()) else {
+                return f64::NAN;
+            };
+            let Some(param) = inner.next().map(|p| p.as_str().to_string()) else {
+                return f64::NAN;
+            };
+            let mut piecewise = false;
+            let pieces = inner
+                .map(|piece| {
+                    let mut piece = piece.into_inner();
+                    let body = piece.next().unwrap().as_str().to_string();
+                    let guard = piece.next();
+                    piecewise |= guard.is_some();
+                    Piece {
+                        param: param.clone(),
+                        condition: guard
+                            .filter(|p| p.as_rule() == Rule::expr)
+                            .map(|p| p.as_str().to_string()),
+                        body,
+                    }
+                })
+                .collect();
+            env.funcs.entry(name).or_default().define(pieces, piecewise);
+            // Function definitions don't produce a numeric result.
+            f64::NAN
+        }
+        Rule::base_font => {
+            let mut inner = pair.into_inner();
+            let Some(size) = inner
+                .next()
+                .map(number)
+                .filter(|n| n.is_finite() && *n > 0.0)
+            else {
+                return f64::NAN;
+            };
+            let unit = match inner.next() {
+                Some(unit) => unit_type(unit),
+                None => Some(UnitType::Typography(Typography::Pixel)),
+            };
+            let Some(unit) = unit else {
+                return f64::NAN;
+            };
+            env.context.base_font_size = convert_with(
+                size,
+                unit,
+                UnitType::Typography(Typography::Pixel),
+                &env.context,
+            );
+            // Settings don't produce a numeric result.
+            f64::NAN
+        }
+        Rule::dpi => {
+            let dpi = pair.into_inner().next().map(number);
+            let Some(dpi) = dpi.filter(|n| n.is_finite() && *n > 0.0) else {
+                return f64::NAN;
+            };
+            env.context.dpi = dpi;
+            f64::NAN
+        }
+        Rule::mode => {
+            let Some(mode) = pair.into_inner().next() else {
+                return f64::NAN;
+            };
+            env.mode = match mode.as_rule() {
+                Rule::int_type => mode.as_str().parse().map_or(Mode::Float, Mode::Integer),
+                Rule::decimal_mode => Mode::Decimal,
+                _ => Mode::Float,
+            };
+            f64::NAN
+        }
+        Rule::rounding => {
+            env.rounding = match pair.into_inner().next().map(|p| p.as_rule()) {
+                Some(Rule::half_up) => Rounding::HalfUp,
+                _ => Rounding::HalfEven,
+            };
+            f64::NAN
+        }
+        Rule::precision => {
+            let digits = pair
+                .into_inner()
+                .next()
+                .and_then(|p| p.as_str().parse().ok());
+            match digits.filter(|digits| (1..=50).contains(digits)) {
+                Some(digits) => env.precision = NonZeroU64::new(digits),
+                None => return f64::NAN,
+            }
+            f64::NAN
+        }
+        Rule::overflow => {
+            env.overflow = match pair.into_inner().next().map(|p| p.as_rule()) {
+                Some(Rule::report) => Overflow::Error,
+                _ => Overflow::Wrap,
+            };
+            f64::NAN
         }
-        Err(_) => f64::NAN,
+        _ => eval(pair.into_inner(), env, None, 0).to_f64(),
     }
 }
 
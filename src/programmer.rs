@@ -0,0 +1,359 @@
+//! Fixed-width integers for programmer mode.
+
+use std::cmp::Ordering;
+use std::fmt;
+
+use num_bigint::{BigInt, Sign};
+
+/// Integer type programmer mode evaluates in, e.g. `u8` or `i64`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct IntType {
+    pub bits: u32,
+    pub signed: bool,
+}
+
+impl IntType {
+    fn mask(self) -> u128 {
+        u128::MAX >> (128 - self.bits)
+    }
+}
+
+impl std::str::FromStr for IntType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let signed = match s.get(..1) {
+            Some("i") => true,
+            Some("u") => false,
+            _ => return Err(format!("Unknown integer type: {s}")),
+        };
+        match s[1..].parse::<u32>() {
+            Ok(bits @ (8 | 16 | 32 | 64 | 128)) => Ok(Self { bits, signed }),
+            _ => Err(format!("Unknown integer type: {s}")),
+        }
+    }
+}
+
+/// What happens when a result doesn't fit its integer type.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum Overflow {
+    /// Keep the low bits, as the hardware does.
+    #[default]
+    Wrap,
+    /// Report the line as an overflow.
+    Error,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum IntError {
+    Overflow,
+    DivisionByZero,
+    NotAnInteger,
+    NegativeShift,
+    /// Something programmer mode can't evaluate, e.g. a unit conversion.
+    Unsupported,
+}
+
+impl fmt::Display for IntError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            IntError::Overflow => "overflow",
+            IntError::DivisionByZero => "division by zero",
+            IntError::NotAnInteger => "not an integer",
+            IntError::NegativeShift => "negative shift",
+            IntError::Unsupported => "not supported",
+        })
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    Power,
+    ShiftLeft,
+    ShiftRight,
+    And,
+    Or,
+    Xor,
+}
+
+/// An integer of a given type, kept as its two's-complement bit pattern.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Int {
+    raw: u128,
+    ty: IntType,
+}
+
+impl Int {
+    /// Builds an integer from its sign and magnitude.
+    pub fn new(
+        negative: bool,
+        magnitude: u128,
+        ty: IntType,
+        overflow: Overflow,
+    ) -> Result<Self, IntError> {
+        let raw = if negative {
+            magnitude.wrapping_neg()
+        } else {
+            magnitude
+        };
+        let int = Self {
+            raw: raw & ty.mask(),
+            ty,
+        };
+        if overflow == Overflow::Error && int.parts() != (negative && magnitude != 0, magnitude) {
+            return Err(IntError::Overflow);
+        }
+        Ok(int)
+    }
+
+    /// Takes a bit pattern as is, the way `0xFF` is read as `-1` in `i8`.
+    pub fn from_bits(bits: u128, ty: IntType, overflow: Overflow) -> Result<Self, IntError> {
+        if overflow == Overflow::Error && bits & !ty.mask() != 0 {
+            return Err(IntError::Overflow);
+        }
+        Ok(Self {
+            raw: bits & ty.mask(),
+            ty,
+        })
+    }
+
+    /// Converts an integer of any size, keeping its low bits when wrapping.
+    pub fn from_bigint(value: &BigInt, ty: IntType, overflow: Overflow) -> Result<Self, IntError> {
+        if overflow == Overflow::Error && value.bits() > 128 {
+            return Err(IntError::Overflow);
+        }
+        let low_bits = value.magnitude().iter_u64_digits().take(2).rev();
+        let magnitude = low_bits.fold(0, |m, digit| (m << 64) | digit as u128);
+        Self::new(value.sign() == Sign::Minus, magnitude, ty, overflow)
+    }
+
+    pub fn from_f64(value: f64, ty: IntType, overflow: Overflow) -> Result<Self, IntError> {
+        if !value.is_finite() || value.fract() != 0.0 {
+            return Err(IntError::NotAnInteger);
+        }
+        if value.abs() >= 2_f64.powi(128) {
+            return Err(IntError::Overflow);
+        }
+        Self::new(value < 0.0, value.abs() as u128, ty, overflow)
+    }
+
+    /// Reads a `num` literal. Radix literals are bit patterns, decimals are values.
+    pub fn parse(text: &str, ty: IntType, overflow: Overflow) -> Result<Self, IntError> {
+        let text = text.trim().replace('_', "");
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, text.strip_prefix('+').unwrap_or(&text)),
+        };
+        let radix = match digits.get(..2) {
+            Some("0x") => 16,
+            Some("0b") => 2,
+            Some("0o") => 8,
+            _ if digits.bytes().all(|b| b.is_ascii_digit()) => {
+                let magnitude = digits.parse().map_err(|_| IntError::Overflow)?;
+                return Self::new(negative, magnitude, ty, overflow);
+            }
+            _ => {
+                let value = text.parse().map_err(|_| IntError::NotAnInteger)?;
+                return Self::from_f64(value, ty, overflow);
+            }
+        };
+        let bits = u128::from_str_radix(&digits[2..], radix).map_err(|_| IntError::Overflow)?;
+        let int = Self::from_bits(bits, ty, overflow)?;
+        match negative {
+            true => Self::new(false, 0, ty, overflow)?.binary(BinaryOp::Subtract, int, overflow),
+            false => Ok(int),
+        }
+    }
+
+    /// The same value in another type.
+    pub fn cast(self, ty: IntType, overflow: Overflow) -> Result<Self, IntError> {
+        let (negative, magnitude) = self.parts();
+        Self::new(negative, magnitude, ty, overflow)
+    }
+
+    pub fn ty(self) -> IntType {
+        self.ty
+    }
+
+    /// Two's-complement bit pattern.
+    pub fn bits(self) -> u128 {
+        self.raw
+    }
+
+    pub fn to_bigint(self) -> BigInt {
+        match self.parts() {
+            (true, magnitude) => -BigInt::from(magnitude),
+            (false, magnitude) => BigInt::from(magnitude),
+        }
+    }
+
+    pub fn to_f64(self) -> f64 {
+        match self.parts() {
+            (true, magnitude) => -(magnitude as f64),
+            (false, magnitude) => magnitude as f64,
+        }
+    }
+
+    fn signed(self) -> i128 {
+        let shift = 128 - self.ty.bits;
+        ((self.raw << shift) as i128) >> shift
+    }
+
+    fn parts(self) -> (bool, u128) {
+        if self.ty.signed {
+            let value = self.signed();
+            (value < 0, value.unsigned_abs())
+        } else {
+            (false, self.raw)
+        }
+    }
+
+    /// Orders two integers by value, whatever their types.
+    pub fn compare(self, rhs: Int) -> Ordering {
+        match (self.parts(), rhs.parts()) {
+            ((false, a), (false, b)) => a.cmp(&b),
+            ((true, a), (true, b)) => b.cmp(&a),
+            ((true, _), _) => Ordering::Less,
+            ((false, _), _) => Ordering::Greater,
+        }
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.raw == 0
+    }
+
+    pub fn not(self) -> Self {
+        Self {
+            raw: !self.raw & self.ty.mask(),
+            ty: self.ty,
+        }
+    }
+
+    pub fn rotate_left(self, amount: Int) -> Self {
+        self.rotate(self.rotation(amount))
+    }
+
+    pub fn rotate_right(self, amount: Int) -> Self {
+        let bits = self.ty.bits;
+        self.rotate((bits - self.rotation(amount)) % bits)
+    }
+
+    /// A rotate amount as a left rotation in `0..bits`.
+    fn rotation(self, amount: Int) -> u32 {
+        let bits = self.ty.bits;
+        let (negative, magnitude) = amount.parts();
+        let n = (magnitude % bits as u128) as u32;
+        if negative { (bits - n) % bits } else { n }
+    }
+
+    fn rotate(self, n: u32) -> Self {
+        if n == 0 {
+            return self;
+        }
+        Self {
+            raw: ((self.raw << n) | (self.raw >> (self.ty.bits - n))) & self.ty.mask(),
+            ty: self.ty,
+        }
+    }
+
+    pub fn binary(self, op: BinaryOp, rhs: Int, overflow: Overflow) -> Result<Self, IntError> {
+        let ty = self.ty;
+        let with_raw = |raw: u128| Self {
+            raw: raw & ty.mask(),
+            ty,
+        };
+
+        match op {
+            BinaryOp::And => return Ok(with_raw(self.raw & rhs.raw)),
+            BinaryOp::Or => return Ok(with_raw(self.raw | rhs.raw)),
+            BinaryOp::Xor => return Ok(with_raw(self.raw ^ rhs.raw)),
+            BinaryOp::ShiftLeft | BinaryOp::ShiftRight => {
+                // The amount is read as signed even in unsigned types, where
+                // `-1` has wrapped to the largest value.
+                let amount = rhs.signed();
+                if amount < 0 {
+                    return Err(IntError::NegativeShift);
+                }
+                let magnitude = amount as u128;
+                // Shifting by the width or more shifts every bit out.
+                let all_out = magnitude >= ty.bits as u128;
+                if overflow == Overflow::Error && op == BinaryOp::ShiftLeft && all_out {
+                    return Err(IntError::Overflow);
+                }
+                let n = magnitude.min(ty.bits as u128 - 1) as u32;
+                return Ok(match op {
+                    BinaryOp::ShiftLeft if all_out => with_raw(0),
+                    BinaryOp::ShiftLeft => with_raw(self.raw << n),
+                    // An arithmetic shift keeps the sign, ending at 0 or -1.
+                    _ if ty.signed => with_raw((self.signed() >> n) as u128),
+                    _ if all_out => with_raw(0),
+                    _ => with_raw(self.raw >> n),
+                });
+            }
+            BinaryOp::Divide | BinaryOp::Remainder if rhs.raw == 0 => {
+                return Err(IntError::DivisionByZero);
+            }
+            _ => {}
+        }
+
+        let exponent = match op {
+            BinaryOp::Power => match rhs.parts() {
+                (true, _) => return Err(IntError::NotAnInteger),
+                (false, e) => u32::try_from(e).map_err(|_| IntError::Overflow)?,
+            },
+            _ => 0,
+        };
+
+        // The exact result, when it fits in 128 bits.
+        let exact = if ty.signed {
+            let (a, b) = (self.signed(), rhs.signed());
+            match op {
+                BinaryOp::Add => a.checked_add(b),
+                BinaryOp::Subtract => a.checked_sub(b),
+                BinaryOp::Multiply => a.checked_mul(b),
+                BinaryOp::Divide => a.checked_div(b),
+                BinaryOp::Remainder => a.checked_rem(b),
+                _ => a.checked_pow(exponent),
+            }
+            .map(|v| (v < 0, v.unsigned_abs()))
+        } else {
+            let (a, b) = (self.raw, rhs.raw);
+            match op {
+                BinaryOp::Add => a.checked_add(b),
+                BinaryOp::Subtract => a.checked_sub(b),
+                BinaryOp::Multiply => a.checked_mul(b),
+                BinaryOp::Divide => a.checked_div(b),
+                BinaryOp::Remainder => a.checked_rem(b),
+                _ => a.checked_pow(exponent),
+            }
+            .map(|v| (false, v))
+        };
+
+        match (exact, overflow) {
+            (Some((negative, magnitude)), _) => Self::new(negative, magnitude, ty, overflow),
+            (None, Overflow::Error) => Err(IntError::Overflow),
+            (None, Overflow::Wrap) => Ok(with_raw(match op {
+                BinaryOp::Add => self.raw.wrapping_add(rhs.raw),
+                BinaryOp::Subtract => self.raw.wrapping_sub(rhs.raw),
+                BinaryOp::Multiply => self.raw.wrapping_mul(rhs.raw),
+                BinaryOp::Divide => self.signed().wrapping_div(rhs.signed()) as u128,
+                BinaryOp::Remainder => self.signed().wrapping_rem(rhs.signed()) as u128,
+                _ => self.raw.wrapping_pow(exponent),
+            })),
+        }
+    }
+}
+
+impl fmt::Display for Int {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.parts() {
+            (true, magnitude) => write!(f, "-{magnitude}"),
+            (false, magnitude) => write!(f, "{magnitude}"),
+        }
+    }
+}
@@ -0,0 +1,51 @@
+//! Reductions over lists of values.
+
+use crate::value::Value;
+
+pub fn sum(items: &[Value]) -> Value {
+    items.iter().cloned().fold(Value::integer(0), |a, b| a + b)
+}
+
+pub fn mean(items: &[Value]) -> Value {
+    sum(items) / Value::integer(items.len() as i64)
+}
+
+pub fn median(items: &[Value]) -> Value {
+    let mut sorted = items.to_vec();
+    sorted.sort_by(|a, b| a.to_f64().total_cmp(&b.to_f64()));
+    match sorted.len() {
+        0 => Value::Float(f64::NAN),
+        n if n % 2 == 1 => sorted.swap_remove(n / 2),
+        n => mean(&sorted[n / 2 - 1..=n / 2]),
+    }
+}
+
+/// Sample standard deviation.
+pub fn stdev(items: &[Value]) -> Value {
+    if items.len() < 2 {
+        return Value::Float(f64::NAN);
+    }
+    let mean = mean(items);
+    let squares: Vec<Value> = items
+        .iter()
+        .map(|x| (x.clone() - mean.clone()).pow(Value::integer(2)))
+        .collect();
+    let variance = sum(&squares) / Value::integer(items.len() as i64 - 1);
+    Value::Float(variance.to_f64().sqrt())
+}
+
+pub fn min(items: &[Value]) -> Value {
+    items
+        .iter()
+        .min_by(|a, b| a.to_f64().total_cmp(&b.to_f64()))
+        .cloned()
+        .unwrap_or(Value::Float(f64::NAN))
+}
+
+pub fn max(items: &[Value]) -> Value {
+    items
+        .iter()
+        .max_by(|a, b| a.to_f64().total_cmp(&b.to_f64()))
+        .cloned()
+        .unwrap_or(Value::Float(f64::NAN))
+}
@@ -1,12 +1,18 @@
 #[rustfmt::skip]
 #[cfg(test)]
 mod tests {
-    use crate::parser::{Env, parse, parse_with_env};
+    use crate::Formatter;
+    use crate::parser::{Env, evaluate, parse, parse_with_env};
+
+    fn display(input: &str) -> String {
+        let mut env = Env::default();
+        Formatter::answer(&evaluate(input, &mut env))
+    }
 
     #[test]
     fn precedence_test() {
         assert_eq!(2_f64, parse("2/(3/3)"));
-        assert_eq!(0.000000000001000088900582341_f64, parse("1 + 1e-12 - 1"));
+        assert_eq!(1e-12_f64, parse("1 + 1e-12 - 1"));
         assert_eq!(4096_f64, parse("2^(3*4)"));
         assert_eq!(14_f64, parse("2*(3+4)"));
         assert_eq!(-14_f64, parse("2 - 2^3*2"));
@@ -338,4 +344,942 @@ mod tests {
         assert_eq!(1126400000000000.0, parse("1024 petabyte to kb"));
         assert_eq!(1126400000000000.0, parse("1024 pb to kb"));
     }
+
+    #[test]
+    fn volume() {
+        assert_eq!(0.25_f64, parse("250 millilitres to l"));
+        assert_eq!(0.25_f64, parse("250 milliliter to l"));
+        assert_eq!(0.25_f64, parse("250 ml to l"));
+        assert_eq!(0.25_f64, parse("250 mL to litre"));
+
+        assert_eq!(0.001_f64, parse("1 litres to m3"));
+        assert_eq!(0.001_f64, parse("1 liter to m3"));
+        assert_eq!(0.001_f64, parse("1 l to m3"));
+        assert_eq!(0.001_f64, parse("1 L to cubic metre"));
+
+        assert_eq!(1000_f64, parse("1 cubic meter to l"));
+        assert_eq!(1000_f64, parse("1 m³ to l"));
+
+        assert_eq!(1_f64, parse("1000 cubic centimetres to l"));
+        assert_eq!(1_f64, parse("1000 cm³ to l"));
+        assert_eq!(1_f64, parse("1000 cm3 to l"));
+        assert_eq!(1_f64, parse("1000 cc to l"));
+
+        assert_eq!(16.387064_f64, parse("1 cubic inch to ml"));
+        assert_eq!(16.387064_f64, parse("1 in³ to ml"));
+        assert_eq!(16.387064_f64, parse("1 in3 to ml"));
+
+        assert_eq!(3.785411784_f64, parse("1 us gallon to l"));
+        assert_eq!(3.785411784_f64, parse("1 gallons to l"));
+        assert_eq!(3.785411784_f64, parse("1 gal to l"));
+        assert_eq!(0.946352946_f64, parse("1 quart to l"));
+        assert_eq!(0.946352946_f64, parse("1 qt to l"));
+        assert_eq!(473.17647300000004_f64, parse("1 pint to ml"));
+        assert_eq!(236.58823650000002_f64, parse("1 cup to ml"));
+        assert_eq!(29.573529562500003_f64, parse("1 fl oz to ml"));
+        assert_eq!(14.786764781250001_f64, parse("1 tbsp to ml"));
+        assert_eq!(4.92892159375_f64, parse("1 tsp to ml"));
+        assert_eq!(1_f64, parse("16 tablespoons to cups"));
+        assert_eq!("1000", display("1 l to ml"));
+        assert_eq!("3", display("1 tbsp to tsp"));
+
+        assert_eq!(4.5460899999999995_f64, parse("1 imperial gallon to l"));
+        assert_eq!(4.5460899999999995_f64, parse("1 imp gal to l"));
+        assert_eq!(1.2009499255048548_f64, parse("1 imp gal to gal"));
+        assert_eq!(568.26125_f64, parse("1 imperial pint to ml"));
+        assert_eq!(568.26125_f64, parse("1 imp pt to ml"));
+        assert_eq!(28.413062500000002_f64, parse("1 imp fl oz to ml"));
+        assert_eq!(17.7581640625_f64, parse("1 imp tbsp to ml"));
+
+        // Short volume aliases must not shadow other units.
+        assert_eq!(10160469.088_f64, parse("10000 long ton to kg"));
+        assert_eq!(1_f64, parse("100 cm to meter"));
+    }
+
+    #[test]
+    fn energy() {
+        assert_eq!(3600000_f64, parse("1 kilowatt hour to joules"));
+        assert_eq!(3600000_f64, parse("1 kWh to J"));
+        assert_eq!(3600_f64, parse("1 kWh to kJ"));
+        assert_eq!(3600_f64, parse("1 Wh to J"));
+        assert_eq!(7.2_f64, parse("2 watt hours to kilojoules"));
+
+        assert_eq!(10.5505585262_f64, parse("10 BTU to kJ"));
+        assert_eq!(1055.05585262_f64, parse("1 btu to J"));
+
+        assert_eq!(4184_f64, parse("1 kcal to J"));
+        assert_eq!(4184_f64, parse("1000 cal to joule"));
+        assert_eq!(1_f64, parse("1000 calories to kilocalories"));
+
+        assert_eq!(1.602176634e-19_f64, parse("1 eV to J"));
+    }
+
+    #[test]
+    fn power() {
+        assert_eq!(74.569987158227_f64, parse("100 hp to kW"));
+        assert_eq!(745.69987158227_f64, parse("1 horsepower to watts"));
+        assert_eq!(1000_f64, parse("1 MW to kW"));
+        assert_eq!(1000_f64, parse("1 megawatt to kilowatts"));
+        assert_eq!(293.07107017_f64, parse("1000 BTU/h to W"));
+        assert_eq!(0.9999995213698638_f64, parse("3412.14 btu/hr to kW"));
+    }
+
+    #[test]
+    fn force() {
+        assert_eq!(224.8089430997105_f64, parse("1 kN to lbf"));
+        assert_eq!(22.48089430997105_f64, parse("100 newtons to lbf"));
+        assert_eq!(4.4482216152605_f64, parse("1 pound-force to N"));
+        assert_eq!(1_f64, parse("100000 dyn to N"));
+        assert_eq!(1000_f64, parse("1 kilonewton to newton"));
+
+        // Pound-force must not shadow the plain pound.
+        assert_eq!(4535.92_f64, parse("10000 pound to kg"));
+    }
+
+    #[test]
+    fn pressure() {
+        assert_eq!(101.325_f64, parse("1 atm to kPa"));
+        assert_eq!(1_f64, parse("101.325 kilopascals to atmospheres"));
+        assert_eq!(14.503773773021683_f64, parse("1 bar to psi"));
+        assert_eq!(1.013529322095696_f64, parse("14.7 psi to bar"));
+        assert_eq!(1.0000001424663214_f64, parse("760 mmHg to atm"));
+        assert_eq!(101325_f64, parse("760 torr to Pa"));
+        assert_eq!(100000_f64, parse("1 bar to pascals"));
+    }
+
+    #[test]
+    fn frequency() {
+        assert_eq!(2400_f64, parse("2.4 GHz to MHz"));
+        assert_eq!(1000_f64, parse("1 kHz to hertz"));
+        assert_eq!(1000_f64, parse("1 megahertz to kilohertz"));
+        assert_eq!(50_f64, parse("3000 rpm to Hz"));
+        assert_eq!(3000_f64, parse("50 Hz to rpm"));
+    }
+
+    #[test]
+    fn electrical() {
+        assert_eq!(1.5_f64, parse("1500 mV to V"));
+        assert_eq!(11000_f64, parse("11 kV to volts"));
+
+        assert_eq!(0.25_f64, parse("250 mA to A"));
+        assert_eq!(0.25_f64, parse("250 µA to mA"));
+        assert_eq!(1000_f64, parse("1 amp to mA"));
+
+        assert_eq!(4700_f64, parse("4.7 kΩ to Ω"));
+        assert_eq!(1000_f64, parse("1 MΩ to kΩ"));
+        assert_eq!(10000_f64, parse("10 ohms to milliohms"));
+
+        assert_eq!(7200_f64, parse("2000 mAh to coulombs"));
+        assert_eq!(3000_f64, parse("3 Ah to mAh"));
+
+        assert_eq!(0.10000000000000002_f64, parse("100 nF to µF"));
+        assert_eq!(1000000_f64, parse("1 farad to uF"));
+        assert_eq!(1000.0000000000001_f64, parse("1 nF to pF"));
+
+        // Bare C and F still mean temperatures.
+        assert_eq!(212_f64, parse("100 C to F"));
+    }
+
+    #[test]
+    fn data_rate() {
+        assert_eq!(12.5_f64, parse("100 Mbps to MB/s"));
+        assert_eq!(1000_f64, parse("1 Gbps to Mbps"));
+        assert_eq!(125_f64, parse("1 Gbit/s to MB/s"));
+        assert_eq!(12500_f64, parse("100 megabits per second to kilobytes per second"));
+        assert_eq!(8_f64, parse("1 B/s to bps"));
+
+        // Binary size aliases.
+        assert_eq!(1.0_f64, parse("1024 KiB to MiB"));
+        assert_eq!(1023.8322211630124_f64, parse("1 TiB to GiB"));
+    }
+
+    #[test]
+    fn transfer_time() {
+        assert_eq!(859.3429999180798_f64, parse("10 GB at 100 Mbps"));
+        assert_eq!(14.322383331967997_f64, parse("10 GB at 100 Mbps to minutes"));
+        assert_eq!(9.7758116927446_f64, parse("4 TiB / 1 Gbps to hours"));
+        assert_eq!(9.7758116927446_f64, parse("4 TiB / 1 Gbps in hours"));
+        assert_eq!(52.428934218071596_f64, parse("500 MB at 10 MB/s"));
+        assert_eq!(17.902979164959998_f64 * 2_f64, parse("2 * (1 GB / 8 Mbps to min)"));
+    }
+
+    #[test]
+    fn fuel_economy() {
+        assert_eq!(7.840486119046173_f64, parse("30 mpg to l/100km"));
+        assert_eq!(30.155715842485282_f64, parse("7.8 l/100km to mpg"));
+        assert_eq!(5.88036458928463_f64, parse("40 miles per gallon to litres per 100 km"));
+        assert_eq!(10_f64, parse("10 l/100km to km/l"));
+        assert_eq!(10_f64, parse("10 L/100 km to kmpl"));
+        assert_eq!(35.28218753570778_f64, parse("15 km/L to mpg"));
+        assert_eq!(41.63370916366405_f64, parse("50 imp mpg to mpg"));
+        assert_eq!(5_f64, parse("5 l/100km to l/100km"));
+    }
+
+    #[test]
+    fn typography() {
+        assert_eq!(1.5_f64, parse("24 px to rem"));
+        assert_eq!(16_f64, parse("12 pt to px"));
+        assert_eq!(24_f64, parse("1.5 em to px"));
+        assert_eq!(12_f64, parse("1 pc to pt"));
+        assert_eq!(96_f64, parse("1 in to px"));
+        assert_eq!(2.645833333333333_f64, parse("100 px to cm"));
+
+        // Inches and centimetres keep converting as lengths.
+        assert_eq!(2.54_f64, parse("1 in to cm"));
+    }
+
+    #[test]
+    fn typography_settings() {
+        let mut env = Env::default();
+        assert!(parse_with_env("base font = 18 px", &mut env).is_nan());
+        assert_eq!(27_f64, parse_with_env("1.5 em to px", &mut env));
+        assert_eq!(1.3333333333333333_f64, parse_with_env("24 px to rem", &mut env));
+
+        assert!(parse_with_env("dpi = 72", &mut env).is_nan());
+        assert_eq!(12_f64, parse_with_env("12 pt to px", &mut env));
+
+        let mut env = Env::default();
+        let _ = parse_with_env("base font = 12 pt", &mut env);
+        assert_eq!(16_f64, parse_with_env("1 rem to px", &mut env));
+
+        // Sizes and resolutions that aren't positive are ignored.
+        let mut env = Env::default();
+        let _ = parse_with_env("dpi = 0", &mut env);
+        let _ = parse_with_env("dpi = -96", &mut env);
+        let _ = parse_with_env("base font = 0", &mut env);
+        let _ = parse_with_env("base font = -2 em", &mut env);
+        assert_eq!(12_f64, parse_with_env("9 pt to px", &mut env));
+        assert_eq!(16_f64, parse_with_env("1 rem to px", &mut env));
+    }
+
+    #[test]
+    fn dms_input() {
+        assert_eq!(12.504166666666666_f64, parse("12°30'15\""));
+        assert_eq!(12.504166666666666_f64, parse("12d 30m 15s"));
+        assert_eq!(12.504166666666666_f64, parse("12d30m15s"));
+        assert_eq!(-12.5_f64, parse("-12°30'"));
+        assert_eq!(13_f64, parse("12° + 1"));
+        assert_eq!(10.508333333333333_f64, parse("10°30.5'"));
+        assert_eq!(12.504236111111112_f64, parse("12°30'15.25\""));
+        assert_eq!(10.5_f64, parse("10.5°"));
+
+        // Only the last part may have a fraction, and minutes and seconds stay below 60.
+        assert!(parse("10°30.5'15\"").is_nan());
+        assert!(parse("10°60'").is_nan());
+        assert!(parse("10°59'60\"").is_nan());
+
+        assert_eq!(0.21823887855145763_f64, parse("12°30'15\" to rad"));
+        assert_eq!(12.5_f64, parse("12d 30m to deg"));
+
+        // Plain angle and time units are unaffected.
+        assert_eq!(0.20943951023931956_f64, parse("12deg to rad"));
+        assert_eq!(288_f64, parse("12 days to hours"));
+    }
+
+    #[test]
+    fn dms_output() {
+        assert_eq!("12°30'15\"", display("12.504166666666666 to dms"));
+        assert_eq!("12°30'0\"", display("12.5 to dms"));
+        assert_eq!("-12°30'0\"", display("-12.5 to dms"));
+        assert_eq!("0°0'0.36\"", display("0.0001 to dms"));
+        assert_eq!("1°0'0\"", display("0.99999999 to dms"));
+        assert_eq!("12°30'15\"", display("12d 30m 15s to dms"));
+        assert_eq!("-", display("x to dms"));
+    }
+
+    #[test]
+    fn geo() {
+        // London to Paris, in kilometres and degrees.
+        assert_eq!(343.55653488088325_f64, parse("distance(51.5074, -0.1278, 48.8566, 2.3522)"));
+        assert_eq!(148.11561687105336_f64, parse("bearing(51.5074, -0.1278, 48.8566, 2.3522)"));
+        assert_eq!(343.47241135000326_f64, parse("distance(48°51'24\", 2°21'3\", 51°30'26\", -0°7'39\")"));
+
+        assert_eq!(10007.557221017962_f64, parse("distance(0, 0, 0, 90)"));
+        assert_eq!(90_f64, parse("bearing(0, 0, 0, 90)"));
+        assert_eq!(0_f64, parse("bearing(0, 0, 10, 0)"));
+        assert_eq!(180_f64, parse("bearing(0, 0, -10, 0)"));
+        assert_eq!(273.6871323393308_f64, parse("bearing(40.7128, -74.0060, 34.0522, -118.2437)"));
+
+        assert!(parse("distance(0, 0, 0)").is_nan());
+
+        // Results keep their unit, so they can be converted.
+        assert_eq!(213.4766642728592_f64, parse("distance(51.5074, -0.1278, 48.8566, 2.3522) to mi"));
+        assert_eq!(2.5851051880223426_f64, parse("bearing(51.5074, -0.1278, 48.8566, 2.3522) to rad"));
+        assert!(parse("distance(51.5074, -0.1278, 48.8566, 2.3522) to rad").is_nan());
+        assert_eq!("distance can't be converted to kg", display("distance(51.5074, -0.1278, 48.8566, 2.3522) to kg"));
+        assert_eq!("bearing can't be converted to km", display("bearing(51.5074, -0.1278, 48.8566, 2.3522) to km"));
+        assert_eq!("only results with a unit can be converted", display("5 to mi"));
+        assert_eq!("1 3/4", display("7/4 to mixed"));
+
+        // Coordinates must be on the globe.
+        assert!(parse("distance(91, 0, 0, 0)").is_nan());
+        assert!(parse("distance(0, 0, 0, -180.5)").is_nan());
+        assert!(parse("bearing(-90.5, 0, 0, 0)").is_nan());
+        assert_eq!(20015.114442035923_f64, parse("distance(90, 180, -90, -180)"));
+    }
+
+    #[test]
+    fn currency() {
+        // Rates from the bundled snapshot, in units per dollar.
+        assert_eq!(85.3_f64, parse("100 usd to eur"));
+        assert_eq!(1.1668611435239207_f64, parse("100 INR to USD"));
+        assert_eq!(6.944444444444444_f64, parse("1000 JPY to usd"));
+        assert_eq!(30_f64, parse("$30"));
+        assert_eq!(20_f64, parse("20 eur"));
+        assert_eq!(39.016060961313016_f64, parse("$30 + €20 in GBP"));
+        assert_eq!(45.589999999999996_f64, parse("€20 + $30"));
+
+        // Prefixes of other units still win where they should.
+        assert_eq!(5000_f64, parse("5 kg to g"));
+        assert_eq!(0.002_f64, parse("2 N to kN"));
+
+        assert_eq!("39.02 GBP", display("$30 + €20 in GBP"));
+        assert_eq!("45.59 EUR", display("€20 + $30"));
+        assert_eq!("85.30 EUR", display("100 usd to eur"));
+        assert_eq!("6.94 USD", display("1000 JPY to usd"));
+        assert_eq!("85.30 EUR", display("x = 100 usd to eur"));
+    }
+
+    #[test]
+    fn currency_rates() {
+        let mut env = Env::default();
+        assert!(parse_with_env("rate EUR/USD = 1.08", &mut env).is_nan());
+        assert_eq!(108_f64, parse_with_env("100 eur to usd", &mut env));
+        assert_eq!(108_f64, parse_with_env("€100 in USD", &mut env));
+        assert_eq!(20_f64, parse_with_env("x = €20", &mut env));
+        assert_eq!(21_f64, parse_with_env("x + 1", &mut env));
+
+        assert_eq!(85.3_f64, parse("100 usd to eur"));
+
+        assert_eq!("EUR can't have a rate against itself", display("rate EUR/EUR = 2"));
+        assert_eq!("a rate must be positive", display("rate EUR/USD = 0"));
+        assert_eq!("a rate must be positive", display("rate EUR/USD = -1.08"));
+
+        // Chained rates can underflow or overflow; such a rate makes the line invalid.
+        assert_eq!("-", eval_lines(&["rate EUR/USD = 1e-200", "rate GBP/EUR = 1e-200", "£100 to usd"]));
+        assert_eq!("-", eval_lines(&["rate EUR/USD = 1e200", "rate GBP/EUR = 1e200", "100 usd to gbp"]));
+    }
+
+    #[test]
+    fn currency_products() {
+        assert_eq!("5", display("€10 / €2"));
+        assert_eq!("60.00 USD", display("2 * $30"));
+        assert_eq!("5.00 USD", display("max($3, $5)"));
+        assert_eq!("amounts of money can't be multiplied together", display("$5 * $3"));
+        assert_eq!("amounts of money can't be multiplied together", display("x = $5 * €3"));
+    }
+
+    #[test]
+    fn physical_constants() {
+        assert_eq!(299792458_f64, parse("phys.c"));
+        assert_eq!(299792458_f64, parse("phys.speed_of_light"));
+        assert_eq!(1.602176634e-19_f64, parse("phys.e"));
+        assert_eq!(6.02214076e23_f64, parse("phys.NA"));
+        assert_eq!(9.80665_f64, parse("phys.g"));
+        assert_eq!(6.6743e-11_f64, parse("phys.G"));
+        assert_eq!(96485.33212331001_f64, parse("phys.e * phys.NA"));
+        assert_eq!(599584916_f64, parse("phys.c * 2"));
+        assert!(parse("phys.nope").is_nan());
+    }
+
+    #[test]
+    fn quantities() {
+        assert_eq!(599584.916_f64, parse("phys.c * 2 s to km"));
+        assert_eq!(120_f64, parse("phys.c * 2 min / phys.c to s"));
+        assert_eq!("299792458 m/s", display("phys.c"));
+        assert_eq!("9.80665 m/s²", display("phys.g"));
+        assert_eq!("6.6743e-11 m³/(kg·s²)", display("phys.G"));
+        assert_eq!("5000 m", display("5 km"));
+        assert_eq!("4 m²", display("2 m ^ 2"));
+        assert_eq!("3 m", display("sqrt(9 m2)"));
+        assert_eq!("98.0665", display("10 kg * phys.g to N"));
+        assert_eq!("3.1068636832", eval_lines(&["d = 5 km", "d to mi"]));
+        assert_eq!("10000 m", eval_lines(&["d = 5 km", "d * 2"]));
+
+        assert_eq!("can't add m and s", display("5 km + 2 s"));
+        assert_eq!("can't add a plain number and m", display("1 + 5 km to mi"));
+        assert_eq!("m/s can't be converted to mi", display("phys.c to mi"));
+        assert_eq!("sin needs plain numbers", display("sin(2 m)"));
+
+        // Money and a bare `in` are not quantities.
+        assert_eq!("85.30 EUR", display("100 in EUR"));
+    }
+
+    #[test]
+    fn constants_are_reserved() {
+        let mut env = Env::default();
+        assert!(parse_with_env("pi = 3", &mut env).is_nan());
+        assert_eq!(std::f64::consts::PI, parse_with_env("pi", &mut env));
+        assert!(parse_with_env("e = 3", &mut env).is_nan());
+        assert_eq!(std::f64::consts::E, parse_with_env("e", &mut env));
+        assert!(parse_with_env("phys.c = 3", &mut env).is_nan());
+        assert_eq!(299792458_f64, parse_with_env("phys.c", &mut env));
+
+        // Names that merely start with a constant are ordinary variables.
+        assert_eq!(4_f64, parse_with_env("energy = 4", &mut env));
+        assert_eq!(6.718281828459045_f64, parse_with_env("energy + e", &mut env));
+        assert_eq!(1_f64, parse_with_env("pie = 1", &mut env));
+        assert_eq!(2_f64, parse_with_env("taut = 2", &mut env));
+        assert_eq!(3_f64, parse_with_env("pie + taut", &mut env));
+    }
+
+    #[test]
+    fn molar_mass() {
+        assert_eq!(98.072_f64, parse("molar_mass(H2SO4)"));
+        assert_eq!(18.015_f64, parse("molar_mass(H2O)"));
+        assert_eq!(74.09200000000001_f64, parse("molar_mass(Ca(OH)2)"));
+        assert_eq!(249.67700000000002_f64, parse("molar_mass(CuSO4·5H2O)"));
+        assert_eq!(249.67700000000002_f64, parse("molar_mass(CuSO4.5H2O)"));
+        assert_eq!(116.88_f64, parse("molar_mass(NaCl) * 2"));
+        assert!(parse("molar_mass(Xx)").is_nan());
+        assert!(parse("molar_mass(Ca(OH2)").is_nan());
+    }
+
+    #[test]
+    fn amount_of_substance() {
+        assert_eq!(0.25_f64, parse("250 mmol to mol"));
+        assert_eq!(0.2775464890369137_f64, parse("5 g H2O to mol"));
+        assert_eq!(36.03_f64, parse("2 mol H2O to g"));
+        assert_eq!(29.22_f64, parse("500 mmol NaCl to g"));
+        assert_eq!(5.550744909966918_f64, parse("1 kg C6H12O6 to mol"));
+        assert_eq!(5000_f64, parse("5 m to mm"));
+    }
+
+    #[test]
+    fn radix_literals() {
+        assert_eq!(255_f64, parse("0xFF"));
+        assert_eq!(255_f64, parse("0xff"));
+        assert_eq!(10_f64, parse("0b1010"));
+        assert_eq!(493_f64, parse("0o755"));
+        assert_eq!(1000000_f64, parse("1_000_000"));
+        assert_eq!(65535_f64, parse("0xFF_FF"));
+        assert_eq!(-16_f64, parse("-0x10"));
+        assert_eq!(256_f64, parse("0xff + 1"));
+        assert_eq!(16_f64, parse("0b1 << 4"));
+        assert_eq!(1000500_f64, parse("1_000.5 kg to g"));
+        assert_eq!(500_f64, parse("0.5e3"));
+    }
+
+    #[test]
+    fn radix_output() {
+        assert_eq!("0xFF", display("255 to hex"));
+        assert_eq!("0b1010", display("10 to bin"));
+        assert_eq!("0b100000000", display("0b1 << 8 in binary"));
+        assert_eq!("0o755", display("493 to oct"));
+        assert_eq!("ZZ", display("1295 to base 36"));
+        assert_eq!("-0x10", display("-16 as hex"));
+        assert_eq!("0b0", display("0 to bin"));
+        assert_eq!("-", display("1.5 to hex"));
+        assert_eq!("-", display("10 to base 37"));
+        assert_eq!("-", display("10 to base 1"));
+    }
+
+    #[test]
+    fn bitwise_operators() {
+        assert_eq!(48_f64, parse("0xF0 & 0x3C"));
+        assert_eq!(252_f64, parse("0xF0 | 0x3C"));
+        assert_eq!(5_f64, parse("6 xor 3"));
+        assert_eq!(-1_f64, parse("~0"));
+        assert_eq!(-6_f64, parse("~5"));
+        assert_eq!(7_f64, parse("1 | 2 + 4"));
+        assert_eq!(3_f64, parse("1 | 2 & 6"));
+    }
+
+    fn eval_lines(lines: &[&str]) -> String {
+        let mut env = Env::default();
+        let mut answer = evaluate("", &mut env);
+        for line in lines {
+            answer = evaluate(line, &mut env);
+        }
+        Formatter::answer(&answer)
+    }
+
+    #[test]
+    fn programmer_mode() {
+        assert_eq!("0", eval_lines(&["mode = u8", "255 + 1"]));
+        assert_eq!("254", eval_lines(&["mode = u8", "3 - 5"]));
+        assert_eq!("-1", eval_lines(&["mode = i8", "0xFF"]));
+        assert_eq!("-64", eval_lines(&["mode = i8", "-128 >> 1"]));
+        // Shifting by the width or more shifts every bit out.
+        assert_eq!("0", eval_lines(&["mode = u8", "1 << 8"]));
+        assert_eq!("0", eval_lines(&["mode = u8", "1 << 9"]));
+        assert_eq!("0", eval_lines(&["mode = u8", "255 >> 8"]));
+        assert_eq!("0", eval_lines(&["mode = i16", "1 << 100"]));
+        assert_eq!("0", eval_lines(&["mode = i16", "1 >> 100"]));
+        assert_eq!("-1", eval_lines(&["mode = i16", "-5 >> 100"]));
+        assert_eq!("0", eval_lines(&["mode = u128", "1 << 128"]));
+        assert_eq!("-1", eval_lines(&["mode = i128", "-1 >> 200"]));
+        assert_eq!("negative shift", eval_lines(&["mode = u8", "1 << -1"]));
+        assert_eq!("negative shift", eval_lines(&["mode = i16", "8 >> -2"]));
+        assert_eq!("-32768", eval_lines(&["mode = i16", "-32768 / -1"]));
+        assert_eq!("-2", eval_lines(&["mode = i32", "-5 % 3"]));
+        assert_eq!("18446744073709551615", eval_lines(&["mode = u64", "2^64 - 1"]));
+        assert_eq!("18446744073709551614", eval_lines(&["mode = u64", "18446744073709551615 - 1"]));
+        assert_eq!("340282366920938463463374607431768211455", eval_lines(&["mode = u128", "2^128 - 1"]));
+        assert_eq!("-170141183460469231731687303715884105728", eval_lines(&["mode = i128", "2^127 - 1 + 1"]));
+        assert_eq!("51", eval_lines(&["mode = u8", "0xF0 & 0x3C | 1 xor 2"]));
+
+        // Variables and user functions keep the integer type.
+        assert_eq!("44", eval_lines(&["mode = u8", "x = 200", "x + 100"]));
+        assert_eq!("144", eval_lines(&["mode = u8", "f(x) = x * 2", "f(200)"]));
+        assert_eq!("256", eval_lines(&["mode = u8", "mode = float", "255 + 1"]));
+
+        assert_eq!("division by zero", eval_lines(&["mode = u8", "5 / 0"]));
+        assert_eq!("not an integer", eval_lines(&["mode = u8", "1.5"]));
+        assert_eq!("not an integer", eval_lines(&["mode = u8", "pi"]));
+        assert_eq!("-", eval_lines(&["mode = u8", "1 kg to g"]));
+    }
+
+    #[test]
+    fn programmer_overflow() {
+        assert_eq!("overflow", eval_lines(&["mode = u8", "overflow = error", "255 + 1"]));
+        assert_eq!("overflow", eval_lines(&["mode = u8", "overflow = error", "3 - 5"]));
+        assert_eq!("overflow", eval_lines(&["mode = i8", "overflow = error", "0x1FF"]));
+        assert_eq!("overflow", eval_lines(&["mode = i16", "overflow = error", "-32768 / -1"]));
+        assert_eq!("overflow", eval_lines(&["mode = u8", "overflow = error", "1 << 8"]));
+        assert_eq!("0", eval_lines(&["mode = u8", "overflow = error", "128 >> 8"]));
+        assert_eq!("255", eval_lines(&["mode = u8", "overflow = error", "254 + 1"]));
+        assert_eq!("0", eval_lines(&["mode = u8", "overflow = error", "overflow = wrap", "255 + 1"]));
+    }
+
+    #[test]
+    fn programmer_output() {
+        assert_eq!("0xFF", eval_lines(&["mode = i8", "-1 to hex"]));
+        assert_eq!("0xFFFFFFFF", eval_lines(&["mode = u32", "~0 to hex"]));
+        assert_eq!("0x000F", eval_lines(&["mode = u16", "15 to hex"]));
+        assert_eq!("0b00000011", eval_lines(&["mode = u8", "rotl(0b10000001, 1) to bin"]));
+        assert_eq!("0b10000000", eval_lines(&["mode = u8", "rotr(1, 1) to bin"]));
+        assert_eq!("0b11111110", eval_lines(&["mode = i8", "-2 to bin"]));
+        assert_eq!("0o377", eval_lines(&["mode = i8", "-1 to oct"]));
+    }
+
+    #[test]
+    fn ieee754() {
+        assert_eq!(
+            "sign 0 · exponent 01111111011 · mantissa 1001100110011001100110011001100110011001100110011010 (2^-4) · exact 0.1000000000000000055511151231257827021181583404541015625 · ulp -1.3877787807814457e-17 / +1.3877787807814457e-17",
+            display("0.1 to ieee754")
+        );
+        assert_eq!(
+            "sign 0 · exponent 01111011 · mantissa 10011001100110011001101 (2^-4) · exact 0.100000001490116119384765625 · ulp -7.450581e-9 / +7.450581e-9",
+            display("0.1 to ieee754 f32")
+        );
+        assert_eq!(display("0.1 to ieee754 f32"), display("0.1 to f32"));
+        assert_eq!(display("0.1 to ieee754"), display("0.1 as double"));
+
+        // Why `sqrt(2)^2` isn't 2.
+        assert!(display("sqrt(2)^2 to ieee754").contains("exact 2.000000000000000444089209850062616169452667236328125 "));
+
+        assert!(display("1 to ieee754").ends_with("exact 1 · ulp -1.1102230246251565e-16 / +2.220446049250313e-16"));
+        assert!(display("-2.5 to f64").contains("sign 1 · exponent 10000000000"));
+        assert!(display("16777217 to f32").contains("exact 16777216 · ulp -1.0 / +2.0"));
+        assert!(display("5e-324 to ieee754").ends_with("3447265625 · ulp -5e-324 / +5e-324"));
+        assert!(display("1/0 to ieee754").ends_with("· inf"));
+        assert!(display("-0 to ieee754").starts_with("sign 1 · exponent 00000000000 · mantissa 0"));
+        assert!(display("-0.0 to f32").starts_with("sign 1 · exponent 00000000 · mantissa 0"));
+        assert_eq!("-", display("x to ieee754"));
+    }
+
+    #[test]
+    fn decimal_mode() {
+        assert_eq!(0.3_f64, parse("0.1 + 0.2"));
+        assert_eq!("0.3", eval_lines(&["mode = decimal", "0.1 + 0.2"]));
+        assert_eq!("0.3333333333333333333333333333333333", eval_lines(&["mode = decimal", "1 / 3"]));
+        assert_eq!("0.6666666666666666666666666666666667", eval_lines(&["mode = decimal", "2 / 3"]));
+        assert_eq!("1000000000000000000000000000001", eval_lines(&["mode = decimal", "10^30 + 1"]));
+        assert_eq!("0.25", eval_lines(&["mode = decimal", "2 ^ -2"]));
+        assert_eq!("220", eval_lines(&["mode = decimal", "10 % on 200"]));
+        assert_eq!("0.3", eval_lines(&["mode = decimal", "x = 0.1", "x * 3"]));
+        assert_eq!("0.11", eval_lines(&["mode = decimal", "f(x) = x * 1.1", "f(0.1)"]));
+        assert_eq!("1255", eval_lines(&["mode = decimal", "0xFF + 1_000"]));
+        assert_eq!("1000", eval_lines(&["mode = decimal", "1 km to m"]));
+        assert_eq!("-", eval_lines(&["mode = decimal", "1 / 0"]));
+        assert_eq!("0.3", eval_lines(&["mode = decimal", "mode = float", "0.1 + 0.2"]));
+    }
+
+    #[test]
+    fn decimal_rounding() {
+        assert_eq!("2.34", eval_lines(&["mode = decimal", "round(2.345, 2)"]));
+        assert_eq!("2.35", eval_lines(&["mode = decimal", "rounding = half up", "round(2.345, 2)"]));
+        assert_eq!("2", eval_lines(&["mode = decimal", "round(2.5)"]));
+        assert_eq!("3", eval_lines(&["mode = decimal", "rounding = half-up", "round(2.5)"]));
+        assert_eq!("0.667", eval_lines(&["mode = decimal", "precision = 3", "2 / 3"]));
+        assert_eq!("2.35", eval_lines(&["mode = decimal", "rounding = half up", "precision = 3", "2.345 * 1"]));
+        assert_eq!("2.34", eval_lines(&["mode = decimal", "precision = 3", "2.345 * 1"]));
+        assert_eq!("1.4142", eval_lines(&["mode = decimal", "precision = 5", "sqrt(2)"]));
+        assert_eq!("3.1416", eval_lines(&["mode = decimal", "precision = 5", "pi"]));
+        assert_eq!("3.1069", eval_lines(&["mode = decimal", "precision = 5", "5 km to mi"]));
+        assert_eq!("-", eval_lines(&["precision = 0"]));
+
+        // Shifts are exact, and a negative amount gives no result.
+        assert_eq!("18446744073709551616", eval_lines(&["mode = decimal", "1 << 64"]));
+        assert_eq!("0", eval_lines(&["mode = decimal", "1 >> 70"]));
+        assert_eq!("-", eval_lines(&["mode = decimal", "1 << -1"]));
+        assert_eq!("2", eval_lines(&["mode = decimal", "6 & 3"]));
+
+        // Huge powers fall back to floating point, and huge exponents are shown in scientific notation.
+        assert_eq!("-", eval_lines(&["mode = decimal", "1.5^100000000000"]));
+        assert_eq!("-", eval_lines(&["mode = decimal", "2^100000000"]));
+        assert_eq!("1.071508607186267320948425049060002e301", eval_lines(&["mode = decimal", "1 << 1000"]));
+        assert_eq!("4.909093465297726553095771954986276e-91", eval_lines(&["mode = decimal", "0.5^300"]));
+    }
+
+    #[test]
+    fn decimal_money() {
+        assert_eq!("25.00 USD", eval_lines(&["mode = decimal", "$19.99 + $5.01"]));
+        assert_eq!("3.33 USD", eval_lines(&["mode = decimal", "$10 / 3"]));
+        assert_eq!("45.59 EUR", eval_lines(&["mode = decimal", "€20 + $30"]));
+        assert_eq!("85.30 EUR", eval_lines(&["mode = decimal", "100 usd to eur"]));
+    }
+
+    #[test]
+    fn rational_arithmetic() {
+        assert_eq!(0.5_f64, parse("1/3 + 1/6"));
+        assert_eq!(1_f64, parse("(1/3) * 3"));
+        assert_eq!("1/2", display("1/3 + 1/6 as fraction"));
+        assert_eq!("2", display("2/3 * 3 as fraction"));
+        assert_eq!("1/8", display("2^-3 as fraction"));
+        assert_eq!("1/3", eval_lines(&["x = 1/3", "x as fraction"]));
+        assert_eq!("3/2", eval_lines(&["f(x) = x / 2", "f(3) as fraction"]));
+        assert_eq!("inf", display("1/0"));
+    }
+
+    #[test]
+    fn fraction_output() {
+        assert_eq!("3/4", display("0.75 as fraction"));
+        assert_eq!("1/10", display("0.1 as fraction"));
+        assert_eq!("-7/4", display("-1.75 as fraction"));
+        assert_eq!("1 3/4", display("1.75 as mixed"));
+        assert_eq!("-1 3/4", display("-7/4 as mixed fraction"));
+        assert_eq!("3/4", display("3/4 as mixed"));
+        assert_eq!("-", display("log(-1) as fraction"));
+        assert_eq!("3/10", display("0.1 + 0.2 to fraction"));
+        assert_eq!("1/400", display("2.5e-3 as fraction"));
+        assert_eq!("1500", display("1.5e3"));
+
+        // Exponents too large to keep exactly fall back to floating point.
+        assert_eq!("10", display("1e16384 / 1e16383"));
+        assert_eq!(f64::INFINITY, parse("1e300000"));
+        assert_eq!(0_f64, parse("1e-300000"));
+        assert!(parse("1e300000 / 1e299999").is_nan());
+    }
+
+    #[test]
+    fn big_integers() {
+        assert_eq!("1267650600228229401496703205376", display("2^100"));
+        assert_eq!("1267650600228229401496703205376", display("1 << 100"));
+        assert_eq!("18446744073709551615", display("2^64 - 1"));
+        assert_eq!("0xFFFFFFFFFFFFFFFF", display("2^64 - 1 to hex"));
+        assert_eq!("0x10000000000000000000000000", display("1 << 100 to hex"));
+        assert_eq!("4", display("2^100 / 2^98"));
+        assert_eq!("-6", display("~5"));
+        assert_eq!("-1", display("-1 >> 1"));
+        assert_eq!("-", display("1 << 70000"));
+        assert_eq!("0", display("1 >> 70000"));
+        assert_eq!("-1", display("-5 >> 70000"));
+        assert_eq!("1", display("2^100 >> 100"));
+    }
+
+    #[test]
+    fn grouped_output() {
+        assert_eq!("1,267,650,600,228,229,401,496,703,205,376", display("2^100 to grouped"));
+        assert_eq!("-1,234,567", display("-1234567 to grouped"));
+        assert_eq!("999", display("999 as grouped"));
+        assert_eq!("1,234,567.891", display("1234567.891 to grouped"));
+        assert_eq!("-12,345,678.5", display("-12345678.5 to grouped"));
+        assert_eq!("1,234,567.25", eval_lines(&["mode = decimal", "1234567.25 to grouped"]));
+        assert_eq!("4,000,000,000", eval_lines(&["mode = u32", "4000000000 to grouped"]));
+    }
+
+    #[test]
+    fn complex_numbers() {
+        assert_eq!("i", display("sqrt(-1)"));
+        assert_eq!("2i", display("sqrt(-4)"));
+        assert_eq!("5 + i", display("(2+3i)*(1-i)"));
+        assert_eq!("-3 + 4i", eval_lines(&["z = 1 + 2i", "z * z"]));
+        assert_eq!("2i", display("(1+i)^2"));
+        assert_eq!(-1_f64, parse("i^2"));
+        assert_eq!(5_f64, parse("abs(3+4i)"));
+        assert_eq!(45_f64, parse("arg(1+i)"));
+        assert_eq!("2 - 3i", display("conj(2+3i)"));
+        assert_eq!(2_f64, parse("re(2+3i)"));
+        assert_eq!(3_f64, parse("im(2+3i)"));
+        assert_eq!(2.54_f64, parse("1 in to cm"));
+        assert_eq!("i is a constant and can't be assigned", display("i = 5"));
+        assert_eq!("pi is a constant and can't be assigned", display("pi = 3"));
+        assert_eq!("i is a constant and can't be a parameter", display("f(i) = i + 1"));
+    }
+
+    #[test]
+    fn polar_form() {
+        assert_eq!("2i", display("polar(2, 90)"));
+        assert_eq!("5 ∠ 53.130102354°", display("3+4i to polar"));
+        assert_eq!("5 ∠ 0.927295218 rad", display("3+4i to polar rad"));
+        assert_eq!("5i", display("5∠90 as rect"));
+        assert_eq!("5 ∠ 90°", display("5∠90° to polar"));
+        assert_eq!("5 ∠ 0.5 rad", display("5∠0.5 rad to polar rad"));
+        assert_eq!("5 ∠ 0°", display("5 to polar"));
+        assert_eq!("8.6602540378 + 5i", display("10∠30°"));
+        assert_eq!("10 ∠ 30°", display("10∠30° to polar"));
+        assert_eq!("5 ∠ -60°", display("5∠-60° to polar"));
+    }
+
+    #[test]
+    fn lists() {
+        assert_eq!("[1, 2, 3]", display("[1, 2, 3]"));
+        assert_eq!("[2, 4, 6]", display("[1, 2, 3] * 2"));
+        assert_eq!("[11, 22, 33]", display("[1, 2, 3] + [10, 20, 30]"));
+        assert_eq!("[9, 8]", display("10 - [1, 2]"));
+        assert_eq!("-", display("[1, 2] + [1, 2, 3]"));
+        assert_eq!("[1, 2, 3i]", display("sqrt([1, 4, -9])"));
+        assert_eq!("[1/2, 1/4]", display("[0.5, 0.25] as fraction"));
+        assert_eq!("[0.85 EUR, 1.71 EUR]", display("[$1, 2] in eur"));
+        assert_eq!("[]", display("[]"));
+        assert_eq!("4", eval_lines(&["xs = [4, 8, 15]", "xs[0]"]));
+        assert_eq!("15", eval_lines(&["xs = [4, 8, 15]", "xs[-1]"]));
+        assert_eq!("-", eval_lines(&["xs = [4, 8, 15]", "xs[3]"]));
+        assert_eq!("3", eval_lines(&["xs = [4, 8, 15]", "len(xs)"]));
+        assert_eq!("[2, 5, 10]", eval_lines(&["f(x) = x^2 + 1", "map(f, [1, 2, 3])"]));
+        assert_eq!("[1, 2, 3]", display("map(sqrt, [1, 4, 9])"));
+        assert_eq!("2.5", eval_lines(&["avg(xs) = sum(xs) / len(xs)", "avg([1, 2, 3, 4])"]));
+    }
+
+    #[test]
+    fn statistics() {
+        assert_eq!(108_f64, parse("sum([4, 8, 15, 16, 23, 42])"));
+        assert_eq!(18_f64, parse("mean([4, 8, 15, 16, 23, 42])"));
+        assert_eq!(15.5_f64, parse("median([4, 8, 15, 16, 23, 42])"));
+        assert_eq!(8_f64, parse("median([15, 4, 8])"));
+        assert_eq!(13.490737563232042_f64, parse("stdev([4, 8, 15, 16, 23, 42])"));
+        assert_eq!(4_f64, parse("min([4, 8, 15, 16, 23, 42])"));
+        assert_eq!(42_f64, parse("max([4, 8, 15, 16, 23, 42])"));
+        assert_eq!(5_f64, parse("max(1, 5, 3)"));
+        assert!(parse("mean([])").is_nan());
+    }
+
+    #[test]
+    fn matrices() {
+        assert_eq!("[[19, 22], [43, 50]]", display("[[1,2],[3,4]] * [[5,6],[7,8]]"));
+        assert_eq!("[17, 39]", display("[[1,2],[3,4]] * [5, 6]"));
+        assert_eq!("[[2, 4], [6, 8]]", display("[[1,2],[3,4]] * 2"));
+        assert_eq!("-", display("[[1,2],[3,4]] * [[1,2,3]]"));
+        assert_eq!("[[1, 4], [2, 5], [3, 6]]", display("transpose([[1,2,3],[4,5,6]])"));
+        assert_eq!(-2_f64, parse("det([[1,2],[3,4]])"));
+        assert_eq!(6_f64, parse("det([[2,0,1],[1,3,2],[1,1,2]])"));
+        assert_eq!(0_f64, parse("det([[1,2],[2,4]])"));
+        assert_eq!("[[-2, 1], [3/2, -1/2]]", display("inv([[1,2],[3,4]]) as fraction"));
+        assert_eq!("[[-2, 1], [1.5, -0.5]]", display("[[1,2],[3,4]]^-1"));
+        assert_eq!("-", display("inv([[1,2],[2,4]])"));
+        assert_eq!("[[89, 55], [55, 34]]", display("[[1,1],[1,0]]^10"));
+        assert_eq!("[[1, 0], [0, 1]]", eval_lines(&["A = [[4, 3], [6, 3]]", "inv(A) * A"]));
+    }
+
+    #[test]
+    fn linear_systems() {
+        assert_eq!("[4/5, 7/5]", display("solve([[2,1],[1,3]], [3, 5]) as fraction"));
+        assert_eq!("[0.8, 1.4]", display("solve([[2,1],[1,3]], [3, 5])"));
+        assert_eq!("[[3, 4], [1, 2]]", display("solve([[0,1],[1,0]], [[1,2],[3,4]])"));
+        assert_eq!("-", display("solve([[1,2],[2,4]], [1, 2])"));
+        assert_eq!("-", display("solve([[1,2],[3,4]], [1, 2, 3])"));
+    }
+
+    #[test]
+    fn comparisons() {
+        assert_eq!(1_f64, parse("3 < 5"));
+        assert_eq!(0_f64, parse("3 > 5"));
+        assert_eq!(1_f64, parse("2 + 2 == 4"));
+        assert_eq!(1_f64, parse("1/3 == 2/6"));
+        assert_eq!(1_f64, parse("0.1 + 0.2 == 0.3"));
+        assert_eq!(0_f64, parse("sqrt(2)^2 == 2"));
+        assert_eq!(1_f64, parse("3 != 4"));
+        assert_eq!(1_f64, parse("5 >= 5"));
+        assert_eq!(0_f64, parse("4 <= 3"));
+        assert_eq!(1_f64, parse("1 << 3 < 10"));
+        assert_eq!("[0, 1, 1]", display("[1, 5, 3] > 2"));
+        assert_eq!(3_f64, parse("sum([1, 5, 3, 8] > 2)"));
+        assert_eq!("1", eval_lines(&["mode = decimal", "0.1 + 0.2 == 0.3"]));
+        assert_eq!("1", eval_lines(&["mode = decimal", "not (1 < 2) or 3 >= 3"]));
+        assert_eq!("1", eval_lines(&["mode = i8", "-1 < 1"]));
+        assert_eq!("1", eval_lines(&["mode = u8", "255 > 1 and not 0"]));
+    }
+
+    #[test]
+    fn boolean_operators() {
+        assert_eq!(1_f64, parse("1 < 2 and 2 < 3"));
+        assert_eq!(0_f64, parse("1 < 2 and 2 > 3"));
+        assert_eq!(1_f64, parse("1 > 2 or 2 < 3"));
+        assert_eq!(1_f64, parse("not 1 > 2"));
+        assert_eq!(0_f64, parse("not 5"));
+        assert_eq!("5", eval_lines(&["order = 4", "order + 1"]));
+    }
+
+    #[test]
+    fn conditionals() {
+        assert_eq!(10_f64, parse("if(1 < 2, 10, 20)"));
+        assert_eq!(20_f64, parse("if(0, 10, 20)"));
+        assert!(parse("if(0/0, 10, 20)").is_nan());
+        assert_eq!("3628800", eval_lines(&["fact(n) = if(n <= 1, 1, n * fact(n - 1))", "fact(10)"]));
+        assert_eq!("610", eval_lines(&["fib(n) = if(n < 2, n, fib(n - 1) + fib(n - 2))", "fib(15)"]));
+        // Deeper recursion stops at the depth guard.
+        assert_eq!("-", eval_lines(&["fact(n) = if(n <= 1, 1, n * fact(n - 1))", "fact(100)"]));
+    }
+
+    #[test]
+    fn piecewise_functions() {
+        let tax = "tax(x) = 0.1x when x < 10000; 0.2x otherwise";
+        assert_eq!("500", eval_lines(&[tax, "tax(5000)"]));
+        assert_eq!("4000", eval_lines(&[tax, "tax(20000)"]));
+        // Pieces on separate lines accumulate, checked in order.
+        let f = ["f(x) = 0 when x < 0", "f(x) = x when x < 10", "f(x) = 10 otherwise"];
+        assert_eq!("0", eval_lines(&[f[0], f[1], f[2], "f(-5)"]));
+        assert_eq!("5", eval_lines(&[f[0], f[1], f[2], "f(5)"]));
+        assert_eq!("10", eval_lines(&[f[0], f[1], f[2], "f(50)"]));
+        assert_eq!("20", eval_lines(&[f[0], f[1], f[2], "f(x) = 20 otherwise", "f(50)"]));
+        // No piece applies.
+        assert_eq!("-", eval_lines(&[f[0], "f(5)"]));
+        // A plain definition replaces the function.
+        assert_eq!("-10", eval_lines(&[f[0], "f(x) = 2x", "f(-5)"]));
+        assert_eq!("225", eval_lines(&["mode = decimal", "price(q) = 2.50q when q < 100; 2.25q otherwise", "price(100)"]));
+        assert_eq!("5", eval_lines(&["mode = i8", "f(x) = 0 - x when x < 0; x otherwise", "f(-5)"]));
+    }
+
+    #[test]
+    fn factorials() {
+        assert_eq!(120_f64, parse("5!"));
+        assert_eq!(1_f64, parse("0!"));
+        assert_eq!(64_f64, parse("2^3!"));
+        assert_eq!(1_f64, parse("5 != 3"));
+        assert_eq!("2432902008176640000", display("20!"));
+        assert_eq!("265252859812191058636308480000000", display("30!"));
+        assert_eq!("0.8862269255", display("0.5!"));
+        assert_eq!("[1, 2, 6, 24]", display("[1, 2, 3, 4]!"));
+        assert!(parse("(0-1)!").is_nan());
+        assert!(parse("6000!").is_infinite());
+        assert_eq!("15511210043330985984000000", eval_lines(&["mode = decimal", "25!"]));
+        assert_eq!("208", eval_lines(&["mode = u8", "6!"]));
+        assert_eq!("overflow", eval_lines(&["mode = u8", "overflow = error", "6!"]));
+    }
+
+    #[test]
+    fn combinations() {
+        assert_eq!(10_f64, parse("nCr(5, 2)"));
+        assert_eq!(2598960_f64, parse("choose(52, 5)"));
+        assert_eq!(0_f64, parse("nCr(5, 7)"));
+        assert_eq!(20_f64, parse("nPr(5, 2)"));
+        assert_eq!(720_f64, parse("perm(10, 3)"));
+        assert_eq!("7.875", display("nCr(4.5, 2)"));
+        assert_eq!("-", display("perm(5, -1)"));
+        assert_eq!("-", display("perm(-3, 2)"));
+        assert_eq!("-", display("choose(-1, 2)"));
+        assert_eq!("100891344545564193334812497256", display("nCr(100, 50)"));
+        assert_eq!("2598960", eval_lines(&["mode = decimal", "nCr(52, 5)"]));
+        assert_eq!("118264581564861424", eval_lines(&["mode = u64", "nCr(60, 30)"]));
+    }
+
+    #[test]
+    fn prime_factors() {
+        assert_eq!("2^3 × 3", display("factor(24)"));
+        assert_eq!("2^3 × 3^2 × 5", display("factor(360)"));
+        assert_eq!("2^3 × 3^2 × 5", display("360 as factors"));
+        assert_eq!("97", display("factor(97)"));
+        assert_eq!("1", display("factor(1)"));
+        assert_eq!("-1 × 2^2 × 3", display("factor(-12)"));
+        assert_eq!("274177 × 67280421310721", display("factor(2^64 + 1)"));
+        assert_eq!("4294967279 × 4294967291", display("factor(4294967291 * 4294967279)"));
+        assert_eq!("-", display("factor(4.5)"));
+        // Composites rho can't split are marked, and inputs over 128 bits are rejected.
+        assert_eq!(
+            "170141183460469232386546718332573188473 (unfactored)",
+            display("factor(nextprime(2^63) * nextprime(2^64))")
+        );
+        assert_eq!("-", display("factor(2^128 + 1)"));
+        assert_eq!("-", display("2^200 to factors"));
+        assert_eq!(361_f64, parse("factor(360) + 1"));
+        assert_eq!("2^3 × 3^2 × 5", eval_lines(&["mode = u64", "factor(360)"]));
+        assert_eq!("2^3 × 3^2 × 5", eval_lines(&["mode = decimal", "factor(360)"]));
+    }
+
+    #[test]
+    fn primes() {
+        assert_eq!(1_f64, parse("isprime(97)"));
+        assert_eq!(0_f64, parse("isprime(1)"));
+        assert_eq!(1_f64, parse("isprime(2^61 - 1)"));
+        assert_eq!(0_f64, parse("isprime(2^61 + 1)"));
+        assert_eq!("[1, 1, 0, 1]", display("isprime([2, 3, 4, 5])"));
+        assert_eq!(101_f64, parse("nextprime(100)"));
+        assert_eq!(2_f64, parse("nextprime(-5)"));
+        assert_eq!(12_f64, parse("totient(36)"));
+        assert_eq!(96_f64, parse("totient(97)"));
+        assert!(parse("isprime(2^128 + 1)").is_nan());
+        assert!(parse("nextprime(2^200)").is_nan());
+        assert_eq!("1", eval_lines(&["mode = u8", "isprime(13)"]));
+    }
+
+    #[test]
+    fn modular_arithmetic() {
+        assert_eq!(6_f64, parse("gcd(12, 18)"));
+        assert_eq!(3_f64, parse("gcd(12, 18, 27)"));
+        assert_eq!(6_f64, parse("gcd([12, 18])"));
+        assert_eq!(60_f64, parse("lcm(4, 6, 10)"));
+        assert!(parse("gcd(1.5, 3)").is_nan());
+        assert_eq!(24_f64, parse("modpow(2, 10, 1000)"));
+        assert_eq!(136318165_f64, parse("modpow(3, 200, 1000000007)"));
+        assert_eq!(5_f64, parse("modpow(3, -1, 7)"));
+        assert_eq!(5_f64, parse("modinv(3, 7)"));
+        assert_eq!(2_f64, parse("modinv(-3, 7)"));
+        assert!(parse("modinv(2, 4)").is_nan());
+        assert_eq!("136318165", eval_lines(&["mode = u64", "modpow(3, 200, 1000000007)"]));
+    }
+
+    #[test]
+    fn logarithms_and_exponentials() {
+        assert_eq!(std::f64::consts::LN_2, parse("ln(2)"));
+        assert_eq!(1_f64, parse("ln(e)"));
+        assert_eq!(3_f64, parse("log2(8)"));
+        assert_eq!(std::f64::consts::E, parse("exp(1)"));
+        assert_eq!("3.1415926536i", display("ln(-1)"));
+        assert_eq!("-1", display("exp(i*pi)"));
+        assert_eq!("0.6931471805599453", eval_lines(&["mode = decimal", "ln(2)"]));
+    }
+
+    #[test]
+    fn basic_functions() {
+        assert_eq!(3_f64, parse("abs(-3)"));
+        assert_eq!(-1_f64, parse("sign(-2.5)"));
+        assert_eq!(0_f64, parse("sign(0)"));
+        assert_eq!(-2_f64, parse("trunc(-2.7)"));
+        assert_eq!(-0.75_f64, parse("frac(-2.75)"));
+        assert!((parse("csc(30)") - 2.0).abs() < 1e-12);
+        assert!((parse("cot(45)") - 1.0).abs() < 1e-12);
+        assert_eq!(180_f64, parse("deg(pi)"));
+        assert_eq!(90_f64, parse("deg(asin(1))"));
+        assert_eq!(std::f64::consts::PI, parse("rad(180)"));
+        assert_eq!("-2", eval_lines(&["mode = decimal", "trunc(-2.75)"]));
+        assert_eq!("-0.75", eval_lines(&["mode = decimal", "frac(-2.75)"]));
+        assert_eq!("-1", eval_lines(&["mode = decimal", "sign(-0.5)"]));
+    }
+
+    #[test]
+    fn unknown_functions() {
+        assert_eq!("unknown function lg, did you mean ln, log?", display("lg(100)"));
+        assert_eq!("unknown function sqr, did you mean sqrt?", display("sqr(4)"));
+        assert_eq!("unknown function sni, did you mean sin?", display("sni(30)"));
+        assert_eq!("unknown function tna, did you mean tan?", display("tna(30)"));
+        assert_eq!("unknown function average", display("average(1, 2)"));
+        assert_eq!("unknown function lnn, did you mean len, ln?", display("y = lnn(2)"));
+        assert_eq!("unknown function g, did you mean f?", eval_lines(&["f(x) = x + 1", "g(2)"]));
+        assert_eq!("unknown function lm, did you mean im, lcm, ln?", eval_lines(&["mode = u8", "lm(2)"]));
+        assert_eq!("unknown function nope", display("map(nope, [1, 2])"));
+        assert_eq!("unknown function foo", display("f(x) = foo(x)"));
+        assert_eq!("unknown function sni, did you mean sin?", display("f(x) = 0 when x < 0; sni(x) otherwise"));
+        assert_eq!("[2, 3]", eval_lines(&["g(x) = x + 1", "map(g, [1, 2])"]));
+        assert_eq!("120", eval_lines(&["f(x) = if(x < 1, 1, x * f(x - 1))", "f(5)"]));
+        assert_eq!("3", eval_lines(&["f(x) = x + 1", "f(2)"]));
+    }
+
+    #[test]
+    fn implicit_multiplication() {
+        assert_eq!("18", eval_lines(&["x = 3", "2x^2"]));
+        assert_eq!(std::f64::consts::TAU, parse("2pi"));
+        assert_eq!(16_f64, parse("0x10"));
+    }
 }
@@ -0,0 +1,419 @@
+//! Numbers the main evaluator works with.
+
+use std::cmp::Ordering;
+use std::ops::{Add, Div, Mul, Rem, Sub};
+
+use num_bigint::BigInt;
+use num_complex::Complex64;
+use num_rational::BigRational;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+
+use crate::matrix;
+use crate::programmer::BinaryOp;
+
+/// Largest exact power, in bits, before falling back to floating point.
+pub const MAX_EXACT_BITS: u64 = 1 << 16;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Comparison {
+    Less,
+    LessEqual,
+    Equal,
+    NotEqual,
+    GreaterEqual,
+    Greater,
+}
+
+impl Comparison {
+    /// Whether the comparison holds for operands ordered as given, where
+    /// `None` means they are unordered, e.g. when one is NaN.
+    pub fn holds(self, ordering: Option<Ordering>) -> bool {
+        match self {
+            Comparison::Less => ordering == Some(Ordering::Less),
+            Comparison::LessEqual => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+            Comparison::Equal => ordering == Some(Ordering::Equal),
+            Comparison::NotEqual => ordering != Some(Ordering::Equal),
+            Comparison::GreaterEqual => {
+                matches!(ordering, Some(Ordering::Greater | Ordering::Equal))
+            }
+            Comparison::Greater => ordering == Some(Ordering::Greater),
+        }
+    }
+}
+
+/// A result that stays exact as long as only rationals are involved.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Float(f64),
+    /// An exact fraction. Integer literals start out as these.
+    Rational(BigRational),
+    /// A complex number with a non-zero imaginary part.
+    Complex(Complex64),
+    /// A list, e.g. `[1, 2, 3]`. Arithmetic on lists is element-wise.
+    List(Vec<Value>),
+}
+
+impl Value {
+    pub fn integer(value: i64) -> Self {
+        Value::Rational(BigRational::from_integer(value.into()))
+    }
+
+    /// A complex number, or a float when it is real.
+    pub fn complex(value: Complex64) -> Self {
+        if value.im == 0.0 {
+            Value::Float(value.re)
+        } else {
+            Value::Complex(value)
+        }
+    }
+
+    /// Reads an integer literal, which may be `0x`, `0b` or `0o` prefixed and
+    /// may group digits with underscores.
+    pub fn parse_integer(text: &str) -> Option<Self> {
+        let text = text.trim().replace('_', "");
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, text.strip_prefix('+').unwrap_or(&text)),
+        };
+        let (radix, digits) = match digits.get(..2) {
+            Some("0x") => (16, &digits[2..]),
+            Some("0b") => (2, &digits[2..]),
+            Some("0o") => (8, &digits[2..]),
+            _ => (10, digits),
+        };
+        let magnitude = BigInt::parse_bytes(digits.as_bytes(), radix)?;
+        let value = if negative { -magnitude } else { magnitude };
+        Some(Value::Rational(BigRational::from_integer(value)))
+    }
+
+    /// Reads a decimal literal such as `0.1` or `2.5e-3` as the exact
+    /// fraction it stands for, so that `0.1 + 0.2` is exactly `3/10`.
+    pub fn parse_decimal(text: &str) -> Option<Self> {
+        let text = text.trim().replace('_', "");
+        let (mantissa, exponent) = match text.split_once(['e', 'E']) {
+            Some((mantissa, exponent)) => (mantissa, exponent.parse::<i64>().ok()?),
+            None => (text.as_str(), 0),
+        };
+        let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let exponent = exponent.checked_sub(fraction.len() as i64)?;
+        // Each power of ten takes a little over three bits.
+        if exponent.unsigned_abs() > MAX_EXACT_BITS / 4 {
+            return None;
+        }
+        let digits: BigInt = format!("{whole}{fraction}").parse().ok()?;
+        let power = BigInt::from(10).pow(exponent.unsigned_abs() as u32);
+        let value = match exponent < 0 {
+            true => BigRational::new(digits, power),
+            false => BigRational::from_integer(digits * power),
+        };
+        Some(Value::Rational(value))
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            Value::Float(value) => *value,
+            Value::Rational(value) => value.to_f64().unwrap_or(f64::NAN),
+            Value::Complex(_) | Value::List(_) => f64::NAN,
+        }
+    }
+
+    /// The element at `index`, counting from zero. Negative indices count
+    /// from the end.
+    pub fn index(&self, index: &Value) -> Value {
+        let (Value::List(items), Some(index)) = (self, index.to_integer()) else {
+            return Value::Float(f64::NAN);
+        };
+        let index = match index.to_i64() {
+            Some(i) if i < 0 => items.len().checked_sub(i.unsigned_abs() as usize),
+            Some(i) => usize::try_from(i).ok(),
+            None => None,
+        };
+        index
+            .and_then(|i| items.get(i))
+            .cloned()
+            .unwrap_or(Value::Float(f64::NAN))
+    }
+
+    pub fn is_zero(&self) -> bool {
+        match self {
+            Value::Float(value) => *value == 0.0,
+            Value::Rational(value) => value.is_zero(),
+            Value::Complex(value) => value.is_zero(),
+            Value::List(_) => false,
+        }
+    }
+
+    /// Absolute value, or modulus of a complex number.
+    pub fn magnitude(&self) -> f64 {
+        match self {
+            Value::Complex(value) => value.norm(),
+            value => value.to_f64().abs(),
+        }
+    }
+
+    pub fn to_complex(&self) -> Complex64 {
+        match self {
+            Value::Complex(value) => *value,
+            value => Complex64::from(value.to_f64()),
+        }
+    }
+
+    /// Raises to a power, exactly when the exponent is an integer and the
+    /// result stays a reasonable size.
+    pub fn pow(self, exponent: Value) -> Value {
+        if let Some(power) = matrix::power(&self, &exponent) {
+            return power;
+        }
+        if self.is_list() || exponent.is_list() {
+            return elementwise(self, exponent, Value::pow);
+        }
+        if let (Value::Rational(base), Value::Rational(exponent)) = (&self, &exponent)
+            && exponent.is_integer()
+            && let Some(n) = exponent.to_i32()
+            && !(n < 0 && base.is_zero())
+        {
+            let bits = base.numer().bits().max(base.denom().bits());
+            if bits * n.unsigned_abs() as u64 <= MAX_EXACT_BITS {
+                return Value::Rational(base.pow(n));
+            }
+        }
+        if matches!(self, Value::Complex(_)) || matches!(exponent, Value::Complex(_)) {
+            let base = self.to_complex();
+            return Value::complex(match exponent.to_integer().and_then(|n| n.to_i32()) {
+                Some(n) => base.powi(n),
+                None => base.powc(exponent.to_complex()),
+            });
+        }
+        Value::Float(self.to_f64().powf(exponent.to_f64()))
+    }
+
+    fn is_list(&self) -> bool {
+        matches!(self, Value::List(_))
+    }
+
+    /// The value as an integer, if it is exactly one.
+    pub fn to_integer(&self) -> Option<BigInt> {
+        match self {
+            Value::Rational(value) if value.is_integer() => Some(value.to_integer()),
+            _ => None,
+        }
+    }
+
+    /// The value as an integer, including floats that happen to be whole.
+    pub fn to_whole(&self) -> Option<BigInt> {
+        match self {
+            Value::Float(x) if x.fract() == 0.0 && x.abs() < 2_f64.powi(53) => {
+                Some(BigInt::from(*x as i64))
+            }
+            _ => self.to_integer(),
+        }
+    }
+
+    /// Whether the value counts as true in a condition, i.e. isn't zero.
+    pub fn is_true(&self) -> bool {
+        !self.is_zero()
+    }
+
+    /// Compares two values, giving 1 when the comparison holds and 0 when it
+    /// doesn't. Lists are compared element by element.
+    pub fn compare(self, op: Comparison, rhs: Value) -> Value {
+        if self.is_list() || rhs.is_list() {
+            return elementwise(self, rhs, |a, b| a.compare(op, b));
+        }
+        let ordering = match (&self, &rhs) {
+            (Value::Rational(a), Value::Rational(b)) => Some(a.cmp(b)),
+            // Complex numbers are only equal or not.
+            (Value::Complex(_), _) | (_, Value::Complex(_)) => {
+                (self.to_complex() == rhs.to_complex()).then_some(Ordering::Equal)
+            }
+            (a, b) => a.to_f64().partial_cmp(&b.to_f64()),
+        };
+        Value::from(op.holds(ordering))
+    }
+
+    /// Shifts and bitwise operators. Exact integers behave as if they had
+    /// infinitely many two's-complement bits; anything else is cast to `i64`.
+    pub fn bitwise(self, op: BinaryOp, rhs: Value) -> Value {
+        if self.is_list() || rhs.is_list() {
+            return elementwise(self, rhs, |a, b| a.bitwise(op, b));
+        }
+        if let (Some(a), Some(b)) = (self.to_integer(), rhs.to_integer()) {
+            let shift = b.to_u64();
+            let result = match (op, shift) {
+                (BinaryOp::And, _) => Some(a & b),
+                (BinaryOp::Or, _) => Some(a | b),
+                (BinaryOp::Xor, _) => Some(a ^ b),
+                (BinaryOp::ShiftLeft, Some(n)) if a.bits() + n <= MAX_EXACT_BITS => {
+                    Some(a << n as usize)
+                }
+                // Shifting every bit out leaves 0, or -1 for a negative number.
+                (BinaryOp::ShiftRight, Some(n)) if n >= a.bits() => {
+                    Some(-BigInt::from(a.is_negative() as u8))
+                }
+                (BinaryOp::ShiftRight, Some(n)) => Some(a >> n as usize),
+                _ => None,
+            };
+            if let Some(result) = result {
+                return Value::Rational(BigRational::from_integer(result));
+            }
+        }
+
+        let (lhs, rhs) = (self.to_f64() as i64, rhs.to_f64() as i64);
+        let shift = |shift: fn(i64, u32) -> Option<i64>| {
+            u32::try_from(rhs)
+                .ok()
+                .and_then(|n| shift(lhs, n))
+                .map_or(f64::NAN, |v| v as f64)
+        };
+        Value::Float(match op {
+            BinaryOp::ShiftLeft => shift(i64::checked_shl),
+            BinaryOp::ShiftRight => shift(i64::checked_shr),
+            BinaryOp::And => (lhs & rhs) as f64,
+            BinaryOp::Or => (lhs | rhs) as f64,
+            BinaryOp::Xor => (lhs ^ rhs) as f64,
+            _ => f64::NAN,
+        })
+    }
+
+    /// Bitwise not, `-x - 1` for exact integers.
+    pub fn not(self) -> Value {
+        if let Value::List(items) = self {
+            return Value::List(items.into_iter().map(Value::not).collect());
+        }
+        match self.to_integer() {
+            Some(value) => Value::Rational(BigRational::from_integer(-value - BigInt::one())),
+            None => Value::Float(!(self.to_f64() as i64) as f64),
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::integer(value as i64)
+    }
+}
+
+impl From<BigInt> for Value {
+    fn from(value: BigInt) -> Self {
+        Value::Rational(BigRational::from_integer(value))
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Float(value)
+    }
+}
+
+macro_rules! arithmetic {
+    ($trait:ident, $method:ident, $op:tt) => {
+        impl $trait for Value {
+            type Output = Value;
+
+            fn $method(self, rhs: Value) -> Value {
+                if self.is_list() || rhs.is_list() {
+                    return elementwise(self, rhs, $trait::$method);
+                }
+                match (self, rhs) {
+                    (Value::Rational(a), Value::Rational(b)) => Value::Rational(a $op b),
+                    (a @ Value::Complex(_), b) | (a, b @ Value::Complex(_)) => {
+                        Value::complex(a.to_complex() $op b.to_complex())
+                    }
+                    (a, b) => Value::Float(a.to_f64() $op b.to_f64()),
+                }
+            }
+        }
+    };
+}
+
+arithmetic!(Add, add, +);
+arithmetic!(Sub, sub, -);
+
+impl Mul for Value {
+    type Output = Value;
+
+    fn mul(self, rhs: Value) -> Value {
+        if let Some(product) = matrix::product(&self, &rhs) {
+            return product;
+        }
+        if self.is_list() || rhs.is_list() {
+            return elementwise(self, rhs, Mul::mul);
+        }
+        match (self, rhs) {
+            (Value::Rational(a), Value::Rational(b)) => Value::Rational(a * b),
+            (a @ Value::Complex(_), b) | (a, b @ Value::Complex(_)) => {
+                Value::complex(a.to_complex() * b.to_complex())
+            }
+            (a, b) => Value::Float(a.to_f64() * b.to_f64()),
+        }
+    }
+}
+
+impl Div for Value {
+    type Output = Value;
+
+    fn div(self, rhs: Value) -> Value {
+        if self.is_list() || rhs.is_list() {
+            return elementwise(self, rhs, Div::div);
+        }
+        match (self, rhs) {
+            (Value::Rational(a), Value::Rational(b)) if !b.is_zero() => Value::Rational(a / b),
+            (a @ Value::Complex(_), b) | (a, b @ Value::Complex(_)) => {
+                Value::complex(a.to_complex() / b.to_complex())
+            }
+            (a, b) => Value::Float(a.to_f64() / b.to_f64()),
+        }
+    }
+}
+
+impl Rem for Value {
+    type Output = Value;
+
+    fn rem(self, rhs: Value) -> Value {
+        if self.is_list() || rhs.is_list() {
+            return elementwise(self, rhs, Rem::rem);
+        }
+        match (self, rhs) {
+            (Value::Rational(a), Value::Rational(b)) if !b.is_zero() => Value::Rational(a % b),
+            (a @ Value::Complex(_), b) | (a, b @ Value::Complex(_)) => {
+                Value::complex(a.to_complex() % b.to_complex())
+            }
+            (a, b) => Value::Float(a.to_f64() % b.to_f64()),
+        }
+    }
+}
+
+/// Applies `op` element by element, pairing up two lists of the same length
+/// or pairing every element of a list with a single value.
+fn elementwise(lhs: Value, rhs: Value, op: impl Fn(Value, Value) -> Value) -> Value {
+    match (lhs, rhs) {
+        (Value::List(a), Value::List(b)) if a.len() == b.len() => {
+            Value::List(a.into_iter().zip(b).map(|(a, b)| op(a, b)).collect())
+        }
+        (Value::List(_), Value::List(_)) => Value::Float(f64::NAN),
+        (Value::List(a), b) => Value::List(a.into_iter().map(|a| op(a, b.clone())).collect()),
+        (a, Value::List(b)) => Value::List(b.into_iter().map(|b| op(a.clone(), b)).collect()),
+        (a, b) => op(a, b),
+    }
+}
+
+/// The simplest fraction that reads back as `value`.
+pub fn fraction(value: f64) -> Option<BigRational> {
+    let exact = BigRational::from_float(value)?;
+    // Walk the convergents of the continued fraction of the exact value; the
+    // last one is the value itself, so this always ends.
+    let (mut h, mut h_prev) = (BigInt::from(1), BigInt::from(0));
+    let (mut k, mut k_prev) = (BigInt::from(0), BigInt::from(1));
+    let mut rest = exact;
+    loop {
+        let a = rest.floor().to_integer();
+        (h, h_prev) = (&a * &h + &h_prev, h);
+        (k, k_prev) = (&a * &k + &k_prev, k);
+        let convergent = BigRational::new(h.clone(), k.clone());
+        let fract = rest - BigRational::from_integer(a);
+        if convergent.to_f64() == Some(value) || fract.is_zero() {
+            return Some(convergent);
+        }
+        rest = fract.recip();
+    }
+}